@@ -2,8 +2,11 @@
 Module for all algorithm related stuff
 '''
 
-from .base import OptimizationAlgorithm
+from .base import OptimizationAlgorithm, AcceptanceDecision, ProgressStats
 from .greedy_search import GreedySearch
 from .local_search import LocalSearch
+from .tie_break import TieBreak
 from .simulated_annealing import SimulatedAnnealing
+from .permutation_local_search import PermutationLocalSearch
+from .memetic import MemeticAlgorithm
 from .util import get_algo_by_name
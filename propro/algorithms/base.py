@@ -3,9 +3,46 @@ Module contains a base definition of an optimization algorithm
 '''
 
 from abc import ABC, abstractmethod
+from dataclasses import dataclass
+from typing import Callable, Optional
 
 from problem.problem import Problem, Solution
-from modes import Mode
+from problem.heuristic import AbstractHeuristic
+from modes import Mode, ScoredMove, NeighborhoodCache, NeighborhoodStats
+
+@dataclass
+class AcceptanceDecision:
+  '''Describes why an algorithm did or did not accept a candidate move'''
+  current_score: AbstractHeuristic
+  '''Score of the solution before the move'''
+  candidate_score: AbstractHeuristic
+  '''Score of the candidate move'''
+  threshold: Optional[float]
+  '''Probability with which the candidate was accepted, `None` if acceptance is not random'''
+  accepted: bool
+  '''Whether the candidate was accepted'''
+
+  def delta(self) -> tuple:
+    '''Difference of the current and the candidate score per field. Positive means the candidate is better.'''
+    return tuple(curr - new for curr, new in zip(self.current_score, self.candidate_score))
+
+  def __str__(self):
+    threshold = f"{self.threshold:.4f}" if self.threshold is not None else "-"
+    decision = "accepted" if self.accepted else "rejected"
+    return f"delta={self.delta()} threshold={threshold} {decision}"
+
+@dataclass
+class ProgressStats:
+  '''Keeps track of how productive the ticks of an algorithm are, to tell improving from churning on a plateau'''
+  best_score: Optional[AbstractHeuristic] = None
+  '''Best score of a complete solution seen so far'''
+  ticks_since_improvement: int = 0
+  '''Number of ticks since the best score was last strictly improved'''
+  improving_ticks: int = 0
+  '''Total number of ticks that strictly improved the best score'''
+
+  def __str__(self):
+    return f"{self.improving_ticks} improving ticks, {self.ticks_since_improvement} since the last one"
 
 class OptimizationAlgorithm(ABC):
   '''
@@ -18,15 +55,112 @@ class OptimizationAlgorithm(ABC):
   '''The strategy chosen for this algorithm'''
   best_solution: Solution
   '''The best solution the algorithm has produced so far'''
+  acceptance_observers: list[Callable[[AcceptanceDecision], None]]
+  '''Callbacks which get notified about every acceptance decision'''
+  neighborhood_cache: Optional[NeighborhoodCache]
+  '''Optional cache for the neighbors of revisited solutions'''
+  progress: ProgressStats
+  '''How many ticks improved the solution, updated by `record_progress`'''
+  candidate_stats: NeighborhoodStats
+  '''
+  Candidates enumerated by the neighborhood for this run, see `get_neighbors`.
+  Neighbors answered from the cache aren't generated again, so they aren't counted either.
+  '''
 
   def __init__(self, problem: Problem):
     self.problem = problem
     self.best_solution = problem.current_solution
+    self.acceptance_observers = []
+    self.neighborhood_cache = None
+    self.progress = ProgressStats()
+    self.candidate_stats = NeighborhoodStats()
+
+  def add_acceptance_observer(self, observer: Callable[[AcceptanceDecision], None]):
+    '''
+    Registers a callback that is notified about every acceptance decision.
+    Computing the decisions is not free, so they are only produced while observers are registered.
+    '''
+    self.acceptance_observers.append(observer)
+
+  def notify_acceptance(self, decision: AcceptanceDecision):
+    '''Hands an acceptance decision to all registered observers'''
+    for observer in self.acceptance_observers:
+      observer(decision)
+
+  def set_neighborhood_cache(self, cache: Optional[NeighborhoodCache]):
+    '''Sets a cache for the neighbors of revisited solutions, `None` to turn caching off'''
+    self.neighborhood_cache = cache
+
+  def get_neighbors(self) -> list[ScoredMove]:
+    '''
+    Returns the neighbors of the current solution for neighborhood based algorithms, using the cache if set.
+    Counts their candidates in `candidate_stats`.
+    '''
+    solution = self.problem.current_solution
+    if self.neighborhood_cache is not None:
+      (neighbors, stats) = self.neighborhood_cache.get_neighbors_with_stats(self.strategy, solution)
+    else:
+      (neighbors, stats) = self.strategy.get_neighbors_with_stats(solution)
+    self.candidate_stats.add(stats)
+    return neighbors
 
   def set_strategy(self, strategy: Mode):
     '''Sets the strategy for the algorithm.'''
     self.strategy = strategy
 
+  def get_state(self) -> dict:
+    '''
+    Returns the internal state of this algorithm as plain values, so a run can be saved and resumed.
+    The current solution is not part of it. Algorithms that keep more than their solution override this.
+    '''
+    return {}
+
+  def set_state(self, state: dict):
+    '''Restores the internal state from a dict as produced by `get_state`'''
+
+  def record_progress(self):
+    '''
+    Compares the score of the current solution to the best one so far and updates the progress counters.
+    Meant to be called once on the starting solution, which becomes the baseline, and after every tick.
+    Incomplete solutions can't be compared and count as no improvement.
+    '''
+    score = self.problem.current_solution.get_heuristic_score()
+    if not self.has_complete_solution() or not score.is_valid():
+      self.progress.ticks_since_improvement += 1
+      return
+    if self.progress.best_score is None or score < self.progress.best_score:
+      # The first complete solution is the baseline, not an improvement
+      if self.progress.best_score is not None:
+        self.progress.improving_ticks += 1
+      self.progress.best_score = score
+      self.progress.ticks_since_improvement = 0
+    else:
+      self.progress.ticks_since_improvement += 1
+
+  def has_complete_solution(self) -> bool:
+    '''
+    Returns whether the current solution is a complete one.
+    Algorithms which build up their solution step by step can override this.
+    '''
+    return True
+
+  def check_lower_bound(self):
+    '''
+    Sanity check that the score of a complete solution never beats the lower bound of the problem.
+    If it does, either the box count or the bound is miscomputed.
+    Only runs in debug mode, i.e. it is skipped with `python -O`.
+    '''
+    if not __debug__:
+      return
+    solution = self.problem.current_solution
+    # Partial, knapsack and overlapping solutions may legitimately use fewer boxes
+    if not self.has_complete_solution() or solution.unplaced_rects or solution.currently_permissible_overlap > 0:
+      return
+    box_count = solution.get_heuristic_score().box_count
+    lower_bound = self.problem.get_lower_bound()
+    assert box_count is None or box_count >= lower_bound, \
+      f"Score uses {box_count} boxes, which is below the lower bound of {lower_bound}"
+
   @abstractmethod
   def tick(self) -> Solution:
     '''
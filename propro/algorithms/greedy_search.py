@@ -5,6 +5,7 @@ from typing import Any
 
 from modes import SelectionSchema, SelectionMove
 from problem.problem import Problem
+from problem.box_problem.geometry import Rectangle
 from .base import OptimizationAlgorithm
 
 class GreedySearch(OptimizationAlgorithm):
@@ -18,6 +19,7 @@ class GreedySearch(OptimizationAlgorithm):
   def __init__(self, problem: Problem, selection_schema: SelectionSchema):
     self.unprocessed_objects = []
     self.strategy = selection_schema
+    problem.check_placeable()
 
     # Init the dict from the given initial solution
     # (this will clear the current solution as well)
@@ -26,6 +28,23 @@ class GreedySearch(OptimizationAlgorithm):
     # Now call the base constructor
     super().__init__(problem)
 
+  def get_state(self) -> dict:
+    return {
+      "unprocessed_rects": [
+        { "id": r.id, "width": r.width, "height": r.height, "value": r.value, "flipped": r.flipped }
+        for r in self.unprocessed_objects.values()
+      ]
+    }
+
+  def set_state(self, state: dict):
+    self.unprocessed_objects = {
+      r["id"]: Rectangle(0, 0, r["width"], r["height"], r["id"], value=r["value"], flipped=r["flipped"])
+      for r in state["unprocessed_rects"]
+    }
+
+  def has_complete_solution(self) -> bool:
+    return len(self.unprocessed_objects) == 0
+
   def tick(self):
     # If there are no unplaced objects we are done here
     if len(self.unprocessed_objects) == 0:
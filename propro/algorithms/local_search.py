@@ -3,10 +3,10 @@ Implementation of a local search algorithm
 '''
 
 import logging
-import random
 
-from modes import Neighborhood, Geometric
-from .base import OptimizationAlgorithm
+from modes import Neighborhood, Geometric, Move, ScoredMove
+from .base import OptimizationAlgorithm, AcceptanceDecision
+from .tie_break import TieBreak
 
 logger = logging.getLogger(__name__)
 
@@ -14,9 +14,18 @@ class LocalSearch(OptimizationAlgorithm):
   '''
   Implements a local search through the solution space
   '''
+  tie_break: TieBreak
+  '''Decides between neighbors that share the best score'''
+  accept_equal: bool
+  '''Whether to move to a neighbor with the same score as the current solution, or only to strictly better ones'''
+  converged: bool
+  '''Set once no neighbor was good enough to move to'''
 
   def __init__(self, problem, neighborhood_definition: Neighborhood = Geometric):
     self.strategy = neighborhood_definition
+    self.tie_break = TieBreak.RANDOM
+    self.accept_equal = True
+    self.converged = False
     problem.current_solution = self.strategy.initialize(problem.current_solution)
     super().__init__(problem)
 
@@ -25,31 +34,62 @@ class LocalSearch(OptimizationAlgorithm):
     logger.debug("Set the neighborhood definition to %s", strategy)
     self.strategy = strategy
 
+  def set_tie_break(self, tie_break: TieBreak):
+    '''Sets the criterion to decide between neighbors with the same score.'''
+    logger.debug("Set the tie break to %s", tie_break)
+    self.tie_break = tie_break
+
+  def get_state(self) -> dict:
+    return { "tie_break": self.tie_break.name, "accept_equal": self.accept_equal, "converged": self.converged }
+
+  def set_state(self, state: dict):
+    self.tie_break = TieBreak[state["tie_break"]]
+    self.accept_equal = state["accept_equal"]
+    self.converged = state["converged"]
+
+  def best_neighbors(self, count: int) -> list[ScoredMove]:
+    '''Returns up to `count` neighbors of the current solution, best first, without moving to any of them'''
+    return sorted(self.get_neighbors(), key=lambda n: n.score)[:count]
+
+  def commit_move(self, move: Move):
+    '''Moves the current solution to a neighbor, e.g. one that was picked by the user'''
+    move.apply_to_solution(self.problem.current_solution)
+
+    # Per definition the current solution will also be the best solution
+    self.best_solution = self.problem.current_solution
+
+    logger.info("Now at score %s", self.problem.current_solution.get_heuristic_score())
+
   def tick(self):
     # Get all possible neighbors
-    neighbors = self.strategy.get_neighbors(self.problem.current_solution)
+    neighbors = self.get_neighbors()
 
     if len(neighbors) == 0:
       logger.info("Algorithm stuck! No neighbors could be found.")
+      self.converged = True
       return
 
     logger.info("Found %i neighbors", len(neighbors))
 
     best_score = min(n.score for n in neighbors)
+    current_score = self.strategy.generate_heuristic(self.problem.current_solution)
 
     logger.info("Chose score %s", best_score)
-    # if best_score > self.problem.current_solution.get_heuristic_score():
-    #   logger.warning("Algorithm reached a local minimum.")
-    #   return
 
-    # Pick one of the best neighbors at random
-    best_neighbors = [n.move for n in neighbors if n.score == best_score]
-    best_neighbor = random.choice(best_neighbors)
+    # Never move to a worse solution, keep the current one instead
+    accept = best_score < current_score or (self.accept_equal and best_score == current_score)
+    self.converged = not accept
 
-    # Actually apply the move
-    best_neighbor.apply_to_solution(self.problem.current_solution)
+    if self.acceptance_observers:
+      self.notify_acceptance(AcceptanceDecision(current_score, best_score, None, accept))
 
-    # Per definition the current solution will also be the best solution
-    self.best_solution = self.problem.current_solution
+    if not accept:
+      logger.info("No neighbor is good enough, algorithm converged.")
+      return
 
-    logger.info("Now at score %s", self.problem.current_solution.get_heuristic_score())
+    # Let the tie break pick one of the best neighbors
+    best_neighbors = [n.move for n in neighbors if n.score == best_score]
+    best_neighbor = self.tie_break.choose(self.problem.current_solution, best_neighbors)
+
+    # Actually apply the move
+    self.commit_move(best_neighbor)
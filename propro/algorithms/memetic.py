@@ -0,0 +1,150 @@
+'''
+Implementation of a memetic algorithm, a genetic algorithm which refines its offspring with a local search
+'''
+
+import logging
+import random
+from copy import copy
+from dataclasses import dataclass
+
+from modes import Neighborhood, Geometric, Permutation, EncodedSolution
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_heuristic import GenericHeuristic
+from problem.box_problem.geometry import Rectangle
+from .base import OptimizationAlgorithm
+from .local_search import LocalSearch
+
+POPULATION_SIZE = 10
+TOURNAMENT_SIZE = 2
+MUTATION_RATE = 0.2
+
+logger = logging.getLogger(__name__)
+
+@dataclass
+class Individual:
+  '''One member of the population'''
+  ordering: list[int]
+  '''Order of rect ids in which the rects are placed into boxes'''
+  solution: BoxSolution
+  '''Decoded and refined solution of the ordering'''
+  score: GenericHeuristic
+
+class MemeticAlgorithm(OptimizationAlgorithm):
+  '''
+  Genetic algorithm on the order in which rectangles are placed into boxes.
+  Each tick breeds one generation of offspring with order crossover and swap mutation.
+  Every offspring is decoded and then refined by a local search for up to `local_search_depth` ticks,
+  the refined packing is encoded back into its ordering. The best individuals of parents and offspring survive.
+  '''
+
+  local_search_depth = 3
+  '''Maximum number of local search ticks to refine each individual with, 0 makes this a plain genetic algorithm'''
+
+  population: list[Individual]
+  '''Current generation, sorted from best to worst'''
+  __rects: dict[int, Rectangle]
+  '''Original rects of the problem by id, to decode orderings from'''
+
+  def __init__(self, problem, neighborhood_definition: type[Neighborhood] = Geometric):
+    self.strategy = neighborhood_definition
+    problem.check_placeable()
+    super().__init__(problem)
+    solution = problem.current_solution
+    rects = [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values())
+    self.__rects = { r.id: r.copy() for r in rects }
+
+    # Start with the largest rects first and fill up the rest with random orderings
+    rect_ids = sorted(self.__rects.keys(), key=lambda i: self.__rects[i].get_area(), reverse=True)
+    self.population = [self.__create_individual(rect_ids)]
+    for _ in range(POPULATION_SIZE - 1):
+      self.population.append(self.__create_individual(random.sample(rect_ids, len(rect_ids))))
+    self.population.sort(key=lambda i: i.score)
+    self.__set_best(self.population[0])
+
+  def get_state(self) -> dict:
+    return {
+      "population": [
+        { "ordering": individual.ordering, "solution": individual.solution.to_dict() }
+        for individual in self.population
+      ]
+    }
+
+  def set_state(self, state: dict):
+    self.population = []
+    for individual in state["population"]:
+      solution = BoxSolution.from_dict(individual["solution"])
+      self.population.append(Individual(individual["ordering"], solution, solution.get_heuristic_score()))
+    self.__set_best(self.population[0])
+
+  def __create_individual(self, ordering: list[int]) -> Individual:
+    '''Decodes an ordering, refines the solution with a local search and encodes it back'''
+    current = self.problem.current_solution
+    encoded = EncodedSolution(
+      [self.__rects[i].copy() for i in ordering], current.side_length, current.grid_unit, current.max_boxes
+    )
+    solution = BoxSolution(
+      current.side_length, Permutation.decode_solution(encoded), grid_unit=current.grid_unit, max_boxes=current.max_boxes
+    )
+    solution.unplaced_rects = Permutation.leftover_rects(encoded)
+
+    if self.local_search_depth > 0:
+      # Run the local search on a problem of its own, so it doesn't touch the current solution
+      sub_problem = copy(self.problem)
+      sub_problem.current_solution = solution
+      local_search = LocalSearch(sub_problem, self.strategy)
+      for _ in range(self.local_search_depth):
+        local_search.tick()
+        if local_search.converged:
+          break
+      self.candidate_stats.add(local_search.candidate_stats)
+      solution = sub_problem.current_solution
+      ordering = [r.id for r in Permutation.encode_solution(solution).rects]
+
+    return Individual(ordering, solution, solution.get_heuristic_score())
+
+  def __set_best(self, individual: Individual):
+    solution = self.problem.current_solution
+    solution.boxes = individual.solution.boxes
+    solution.unplaced_rects = individual.solution.unplaced_rects
+    for box in solution.boxes.values():
+      box.needs_redraw = True
+    self.best_solution = solution
+
+  def __select_parent(self) -> Individual:
+    '''Picks the best of a few random individuals'''
+    return min(random.sample(self.population, TOURNAMENT_SIZE), key=lambda i: i.score)
+
+  @staticmethod
+  def order_crossover(first: list[int], second: list[int]) -> list[int]:
+    '''
+    Copies a random slice of the first parent and fills up the remaining positions
+    with the missing rect ids in the order they appear in the second parent.
+    '''
+    start, end = sorted(random.sample(range(len(first) + 1), 2))
+    middle = first[start:end]
+    taken = set(middle)
+    rest = [i for i in second if i not in taken]
+    return rest[:start] + middle + rest[start:]
+
+  @staticmethod
+  def swap_mutation(ordering: list[int]) -> list[int]:
+    '''Swaps two random positions of the ordering'''
+    ordering = ordering.copy()
+    if len(ordering) >= 2:
+      i, j = random.sample(range(len(ordering)), 2)
+      ordering[i], ordering[j] = ordering[j], ordering[i]
+    return ordering
+
+  def tick(self):
+    # Breed a generation of offspring
+    offspring = []
+    for _ in range(POPULATION_SIZE):
+      ordering = self.order_crossover(self.__select_parent().ordering, self.__select_parent().ordering)
+      if random.random() < MUTATION_RATE:
+        ordering = self.swap_mutation(ordering)
+      offspring.append(self.__create_individual(ordering))
+
+    # Only the best of parents and offspring survive
+    self.population = sorted(self.population + offspring, key=lambda i: i.score)[:POPULATION_SIZE]
+    self.__set_best(self.population[0])
+    logger.info("Now at score %s", self.population[0].score)
@@ -0,0 +1,103 @@
+'''
+Implementation of a local search on the permutation encoding of a solution
+'''
+
+import logging
+import random
+from itertools import combinations
+
+from modes import Permutation, EncodedSolution
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_heuristic import GenericHeuristic
+from .base import OptimizationAlgorithm, AcceptanceDecision
+
+MAX_SWAPS_PER_TICK = 200
+'''Maximum number of swaps to evaluate per tick, above that a random sample is taken'''
+
+logger = logging.getLogger(__name__)
+
+class PermutationLocalSearch(OptimizationAlgorithm):
+  '''
+  Local search which keeps the order of rectangles as its state.
+  Each tick tries swapping pairs of rectangles in the ordering, decodes each of them
+  to a solution for scoring and keeps the swap which improves the score the most.
+  '''
+
+  encoding: EncodedSolution
+  '''Current order in which the rectangles are placed into boxes'''
+
+  def __init__(self, problem, neighborhood_definition: type[Permutation] = Permutation):
+    self.strategy = neighborhood_definition
+    problem.check_placeable()
+    problem.current_solution = self.strategy.initialize(problem.current_solution)
+    super().__init__(problem)
+    self.encoding = self.strategy.encode_solution(problem.current_solution)
+
+  def get_state(self) -> dict:
+    return { "ordering": [r.id for r in self.encoding.rects] }
+
+  def set_state(self, state: dict):
+    # The encoding holds the rect objects of the current solution itself
+    solution = self.problem.current_solution
+    self.encoding = EncodedSolution(
+      [solution.find_rectangle(rect_id)[1] for rect_id in state["ordering"]],
+      solution.side_length,
+      solution.grid_unit,
+      solution.max_boxes
+    )
+
+  def __score_encoding(self, encoding: EncodedSolution) -> GenericHeuristic:
+    '''Decodes a copy of the encoding and scores the resulting solution'''
+    decoding = encoding.copy()
+    boxes = self.strategy.decode_solution(decoding)
+    solution = BoxSolution(encoding.side_length, boxes, grid_unit=encoding.grid_unit, max_boxes=encoding.max_boxes)
+    solution.unplaced_rects = self.strategy.leftover_rects(decoding)
+    return GenericHeuristic(
+      len(solution.unplaced_rects),
+      len(solution.boxes),
+      solution.compute_box_entropy(),
+      solution.compute_incident_edge_coordinates()
+    )
+
+  def tick(self):
+    current_score = self.__score_encoding(self.encoding)
+    ordering = self.encoding.rects
+
+    # Swapping two rects of the same dimensions won't change anything
+    swaps = [
+      (i, j) for i, j in combinations(range(len(ordering)), 2)
+      if {ordering[i].width, ordering[i].height} != {ordering[j].width, ordering[j].height}
+    ]
+    if len(swaps) > MAX_SWAPS_PER_TICK:
+      swaps = random.sample(swaps, MAX_SWAPS_PER_TICK)
+
+    logger.info("Evaluating %i swaps", len(swaps))
+
+    best_swap = None
+    best_score = current_score
+    for (i, j) in swaps:
+      candidate = EncodedSolution(ordering.copy(), self.encoding.side_length, self.encoding.grid_unit, self.encoding.max_boxes)
+      candidate.rects[i], candidate.rects[j] = candidate.rects[j], candidate.rects[i]
+      score = self.__score_encoding(candidate)
+      if score < best_score:
+        best_swap = (i, j)
+        best_score = score
+
+    if self.acceptance_observers:
+      self.notify_acceptance(AcceptanceDecision(current_score, best_score, None, best_swap is not None))
+
+    if best_swap is None:
+      logger.info("No improving swap found, algorithm converged.")
+      return
+
+    # Apply the swap to the actual ordering and decode it into the current solution
+    i, j = best_swap
+    ordering[i], ordering[j] = ordering[j], ordering[i]
+    ordering[i].highlighted = True
+    ordering[j].highlighted = True
+    solution = self.problem.current_solution
+    solution.boxes = { box.id: box for box in self.strategy.decode_solution(self.encoding) }
+    solution.unplaced_rects = self.strategy.leftover_rects(self.encoding)
+
+    self.best_solution = solution
+    logger.info("Now at score %s", best_score)
@@ -8,7 +8,7 @@ from math import exp
 
 from modes import Neighborhood, Geometric, ScoredMove
 
-from .base import OptimizationAlgorithm
+from .base import OptimizationAlgorithm, AcceptanceDecision
 
 START_TEMP = 100.0
 TEMP_STEPS = 10
@@ -36,6 +36,13 @@ class SimulatedAnnealing(OptimizationAlgorithm):
     self.strategy = strategy
 
 
+  def get_state(self) -> dict:
+    return { "temperature": self.temperature, "inner_loop_counter": self.inner_loop_counter }
+
+  def set_state(self, state: dict):
+    self.temperature = state["temperature"]
+    self.inner_loop_counter = state["inner_loop_counter"]
+
   def __accept_solution(self, scored_move: ScoredMove):
     '''Checks whether a new solution shall be accepted or not'''
     # NOTE: Not generic in the sense of the assignment, but needs to work on all score fields
@@ -43,18 +50,23 @@ class SimulatedAnnealing(OptimizationAlgorithm):
     # If score is better, apply move
     if current_score > scored_move.score:
       scored_move.move.apply_to_solution(self.problem.current_solution)
+      if self.acceptance_observers:
+        self.notify_acceptance(AcceptanceDecision(current_score, scored_move.score, 1.0, True))
       return
 
     # Draw a random number
     rand = random.random()
 
-    curr_score_iter = iter(current_score)
-    scored_score_iter = iter(scored_move.score)
-    for curr, new in zip(curr_score_iter, scored_score_iter):
-      delta = curr - new
-      if exp(delta / self.temperature) > rand:
-        scored_move.move.apply_to_solution(self.problem.current_solution)
-        return
+    # The first field that differs decides between the scores, so that is the one to anneal on.
+    # The candidate isn't better, so whichever way the field counts, it is worse by the difference there
+    delta = next((-abs(curr - new) for curr, new in zip(current_score, scored_move.score) if curr != new), 0)
+    probability = exp(delta / self.temperature)
+    accepted = probability > rand
+    if accepted:
+      scored_move.move.apply_to_solution(self.problem.current_solution)
+
+    if self.acceptance_observers:
+      self.notify_acceptance(AcceptanceDecision(current_score, scored_move.score, probability, accepted))
 
   def __update_temperature(self):
     '''Can be called to update the temperature after each algorithm tick'''
@@ -74,7 +86,7 @@ class SimulatedAnnealing(OptimizationAlgorithm):
 
   def tick(self):
      # Get all possible neighbors
-    neighbors = self.strategy.get_neighbors(self.problem.current_solution)
+    neighbors = self.get_neighbors()
 
     if len(neighbors) == 0:
       logger.warning("Algorithm stuck! No neighbors could be found.")
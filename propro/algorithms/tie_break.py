@@ -0,0 +1,60 @@
+'''
+Policies to choose between neighbors that share the best score
+'''
+
+import random
+from enum import Enum
+
+from modes import Move
+from problem.box_problem.box_solution import BoxSolution
+
+class TieBreak(Enum):
+  '''Secondary criterion to decide between neighbors with the same score'''
+
+  RANDOM = "Random"
+  '''Pick one of them at random'''
+  MAX_DENSITY = "Highest density"
+  '''Prefer the neighbor whose fullest box is filled the most'''
+  EMPTY_BOX = "Empty a box"
+  '''Prefer the neighbor whose emptiest box holds the least area, to work towards emptying it'''
+  FEWEST_MOVED = "Fewest moved rects"
+  '''
+  Prefer the neighbor that changes the position of the fewest rects.
+  Only tells neighborhoods apart whose moves change several rects, like `Permutation`, `RotateBox` or `DissolveBox`.
+  A move of `Geometric` and the other single rect neighborhoods always changes exactly one rect,
+  so there it is a no-op that always picks the first of the tied neighbors.
+  '''
+
+  def choose(self, solution: BoxSolution, moves: list[Move]) -> Move:
+    '''Chooses one of the given moves, which must all be valid on the solution'''
+    if self == TieBreak.RANDOM or len(moves) == 1:
+      return random.choice(moves)
+
+    # Score every move by applying it, the first of the best ones wins
+    return min(moves, key=lambda move: self.__evaluate(solution, move))
+
+  def __evaluate(self, solution: BoxSolution, move: Move) -> float:
+    '''Returns the criterion of the solution after the move, lower is better'''
+    before = self.__positions(solution) if self == TieBreak.FEWEST_MOVED else None
+    move.apply_to_solution(solution)
+
+    match self:
+      case TieBreak.MAX_DENSITY:
+        value = -max(b.get_fill_ratio() for b in solution.boxes.values())
+      case TieBreak.EMPTY_BOX:
+        value = min(b.get_fill_ratio() for b in solution.boxes.values())
+      case TieBreak.FEWEST_MOVED:
+        after = self.__positions(solution)
+        value = sum(1 for rect_id, position in after.items() if before.get(rect_id) != position)
+
+    move.undo(solution)
+    return value
+
+  @staticmethod
+  def __positions(solution: BoxSolution) -> dict[int, tuple[int, int, int, int]]:
+    '''Maps every placed rect to its box, origin and width'''
+    return {
+      rect.id: (box.id, rect.get_x(), rect.get_y(), rect.get_width())
+      for box in solution.boxes.values()
+      for rect in box.rects.values()
+    }
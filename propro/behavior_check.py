@@ -0,0 +1,632 @@
+'''
+Checks of single behaviors the neighborhoods and algorithms rely on, each built from a small hand-made
+or seeded situation whose outcome is known. Complements decode_check.py, which checks one contract on many
+random instances.
+'''
+
+import json
+import random
+import sys
+import threading
+from argparse import ArgumentParser
+from copy import deepcopy
+from typing import Callable
+
+from rich.table import Table
+from rich.console import Console
+
+from algorithms import LocalSearch, SimulatedAnnealing
+from modes import CompoundMove, CoarseToFine, Compact, DissolveBox, Geometric, GeometricOverlap, Knapsack, \
+  Permutation, RotateBox
+from modes.neighborhoods.geometric import GeometricMove
+from modes.neighborhoods.neighborhood import NeighborhoodStats
+from problem.box_problem.box_problem import BoxProblem
+from problem.box_problem.problem_builder import ProblemBuilder
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
+from problem.box_problem.box_heuristic import GenericHeuristic
+from problem.box_problem.geometry import Box, Rectangle
+from worker import SearchWorker
+
+def box_coordinate_violations(box: Box, context: str) -> list[str]:
+  '''Compares the incrementally updated coordinates and incident edges of the box to a full recalculation'''
+  incremental = (set(box.get_adjacent_coordinates()), box.get_incident_edge_count())
+  box.dirty = True
+  full = (set(box.get_adjacent_coordinates()), box.get_incident_edge_count())
+
+  violations = []
+  if incremental[0] != full[0]:
+    violations.append(
+      f"{context}: adjacent coordinates missing {sorted(full[0] - incremental[0])}, "
+      f"extra {sorted(incremental[0] - full[0])}"
+    )
+  if incremental[1] != full[1]:
+    violations.append(f"{context}: {incremental[1]} incident edges, a full recalculation counts {full[1]}")
+  return violations
+
+def check_incremental_coordinates() -> list[str]:
+  '''Adding and removing rects updates the coordinates of a box like a full recalculation would'''
+  # A corner of the first rect lies on the edges of the second one
+  box = Box(0, 6, Rectangle(0, 0, 1, 4, 0))
+  box.add_rect(Rectangle(1, 0, 5, 6, 1))
+  violations = box_coordinate_violations(box, "Corner on a new edge")
+
+  for seed in range(20):
+    rng = random.Random(seed)
+    box = Box(0, 12)
+    for rect_id in range(15):
+      box.fit_rect_compress(Rectangle(0, 0, rng.randrange(1, 6), rng.randrange(1, 6), rect_id))
+      violations += box_coordinate_violations(box, f"Seed {seed} after adding rect {rect_id}")
+    for rect_id in rng.sample(sorted(box.rects), len(box.rects) // 2):
+      box.remove_rect(rect_id)
+      violations += box_coordinate_violations(box, f"Seed {seed} after removing rect {rect_id}")
+  return violations
+
+def check_edge_flush() -> list[str]:
+  '''Rects may sit flush against every edge of a box in both orientations, but not one past it'''
+  side_length = 6
+  violations = []
+  for (width, height) in [(side_length, 2), (2, side_length)]:
+    for (x, y) in [(0, 0), (side_length - width, side_length - height), (0, side_length - height)]:
+      rect = Rectangle(x, y, width, height, 0)
+      if not Box(0, side_length).add_rect(rect.copy()):
+        violations.append(f"{width}x{height} at {x}/{y} doesn't fit into an empty box")
+      if not BoxSolution(side_length, [Box(0, side_length, rect.copy())]).is_valid():
+        violations.append(f"{width}x{height} at {x}/{y} makes the solution invalid")
+
+    # One past the far edge, the rect has to stick out on the one side it is as long as
+    (x, y) = (1, 0) if width == side_length else (0, 1)
+    rect = Rectangle(x, y, width, height, 0)
+    if Box(0, side_length).add_rect(rect.copy()):
+      violations.append(f"{width}x{height} at {x}/{y} sticks out but fits into the box")
+    # Without coordinates the box takes any rect, so only the solution can tell it sticks out
+    if BoxSolution(side_length, [Box(0, side_length, rect.copy(), calc_coords=False)]).is_valid():
+      violations.append(f"{width}x{height} at {x}/{y} sticks out but the solution is valid")
+
+  # The neighborhood enumerating every origin has to reach the far edges in both orientations
+  rects = [Rectangle(0, 0, 2, 3, 0)] + [Rectangle(0, 0, 1, 1, i) for i in range(1, 4)]
+  solution = GeometricOverlap.initialize(BoxSolution(side_length, [Box(0, side_length, *rects, calc_coords=False)]))
+  moves = [m.move for m in GeometricOverlap.generate_moves_for_rects(solution, [(0, 0)], NeighborhoodStats())]
+  for (width, height) in [(2, 3), (3, 2)]:
+    if not any(
+      m.new_width == width and m.new_x + width == side_length and m.new_y + height == side_length for m in moves
+    ):
+      violations.append(f"GeometricOverlap never places a {width}x{height} rect in the far corner")
+  return violations
+
+def check_compound_rollback() -> list[str]:
+  '''A compound move failing at any step leaves the solution exactly as it was, down to the order of its rects'''
+  violations = []
+  for seed in range(10):
+    rng = random.Random(seed)
+    problem = BoxProblem(box_length=10, n_rect=30, w_range=range(2, 7), h_range=range(2, 7), seed=seed)
+    solution = Permutation.initialize(problem.current_solution)
+    before = json.dumps(solution.to_dict())
+
+    # The decoding starts every box at the origin, so moving another rect there always fails
+    box_ids = list(solution.boxes)
+    rng.shuffle(box_ids)
+    (target_box_id, failing_box_id, *moved_box_ids) = box_ids
+    failing_rect = next(iter(solution.boxes[failing_box_id].rects.values()))
+    failing_move = GeometricMove(
+      failing_rect.id, failing_box_id, target_box_id, 0, 0, failing_rect.get_width(), failing_rect.get_height()
+    )
+
+    for failing_at in range(min(len(moved_box_ids), 3) + 1):
+      # Every move before the failing one opens a new box, which always succeeds
+      moves = []
+      for n, box_id in enumerate(moved_box_ids[:failing_at]):
+        rect = rng.choice(list(solution.boxes[box_id].rects.values()))
+        moves.append(GeometricMove(
+          rect.id, box_id, max(solution.boxes) + n + 1, rng.randrange(0, 5), rng.randrange(0, 5),
+          rect.get_height(), rect.get_width()
+        ))
+      if CompoundMove(moves + [failing_move]).apply_to_solution(solution):
+        violations.append(f"Seed {seed}: compound move failing at step {failing_at} succeeded")
+      if json.dumps(solution.to_dict()) != before:
+        violations.append(f"Seed {seed}: compound move failing at step {failing_at} changed the solution")
+  return violations
+
+class UndercountingSolution(BoxSolution):
+  '''Scores like a box solution, but deliberately forgets the boxes that hold a single rect'''
+
+  def get_heuristic_score(self) -> GenericHeuristic:
+    score = super().get_heuristic_score()
+    if score.box_count is not None:
+      score.box_count = sum(1 for box in self.boxes.values() if len(box.rects) > 1)
+    return score
+
+def check_lower_bound_guard() -> list[str]:
+  '''The lower bound check after a tick passes on the actual score and flags a score that miscounts the boxes'''
+  if not __debug__:
+    return ["The lower bound is only checked in debug mode, run without -O"]
+
+  violations = []
+  for broken in [False, True]:
+    problem = BoxProblem(box_length=10, n_rect=20, w_range=range(2, 7), h_range=range(2, 7), seed=0)
+    if broken:
+      solution = problem.current_solution
+      problem.current_solution = UndercountingSolution(solution.side_length, list(solution.boxes.values()))
+    algorithm = LocalSearch(problem, Geometric)
+    try:
+      for _ in range(3):
+        algorithm.tick()
+        algorithm.check_lower_bound()
+    except AssertionError as e:
+      if not broken:
+        violations.append(f"Flagged the actual score: {e}")
+      continue
+    if broken:
+      violations.append("Didn't flag a score that leaves out the boxes with a single rect")
+  return violations
+
+def check_local_optimum_kept() -> list[str]:
+  '''
+  A local search tick whose best neighbor isn't better keeps the current solution and its score.
+  Which neighbors Geometric tries depends on the order of the rects, so this is checked on every such tick
+  of a run instead of on a single converged solution.
+  '''
+  violations = []
+  kept_ticks = 0
+  for seed in range(5):
+    problem = BoxProblem(box_length=8, n_rect=10, w_range=range(2, 6), h_range=range(2, 6), seed=seed)
+    algorithm = LocalSearch(problem, Geometric)
+    algorithm.accept_equal = False
+    decisions = []
+    algorithm.add_acceptance_observer(decisions.append)
+
+    for tick in range(30):
+      solution = algorithm.problem.current_solution
+      # Scoring the neighbors reorders the rects, so only their placement is compared
+      (before, score) = (solution.solution_hash(), solution.get_heuristic_score())
+      decisions.clear()
+      algorithm.tick()
+      if len(decisions) == 0 or decisions[0].candidate_score < decisions[0].current_score:
+        continue
+      kept_ticks += 1
+      if decisions[0].accepted:
+        violations.append(f"Seed {seed}, tick {tick}: accepted a neighbor at {decisions[0].candidate_score}")
+      if solution.solution_hash() != before or solution.get_heuristic_score() != score:
+        violations.append(f"Seed {seed}, tick {tick}: left the solution for a neighbor that isn't better")
+  if kept_ticks == 0:
+    violations.append("No tick ended on a local optimum, the check didn't test anything")
+  return violations
+
+def check_rect_lookups() -> list[str]:
+  '''The lookups of a box's rects and of a rect's box find every packed and unplaced rect, and nothing else'''
+  violations = []
+  # The box limit leaves some rects unplaced
+  rect_number = 30
+  problem = BoxProblem(box_length=8, n_rect=rect_number, w_range=range(2, 6), h_range=range(2, 6), seed=0, max_boxes=3)
+  solution = problem.current_solution
+  if len(solution.unplaced_rects) == 0:
+    violations.append("The instance has no unplaced rects to look up")
+
+  for box in solution.boxes.values():
+    if solution.rectangles_in_box(box.id) != list(box.rects.values()):
+      violations.append(f"Box {box.id}: rectangles_in_box doesn't return its rects")
+    for rect in box.rects.values():
+      if solution.find_rectangle(rect.id) != (box.id, rect):
+        violations.append(f"Rect {rect.id}: not found in box {box.id}, got {solution.find_rectangle(rect.id)}")
+  for rect in solution.unplaced_rects.values():
+    if solution.find_rectangle(rect.id) != (UNPLACED_BOX_ID, rect):
+      violations.append(f"Rect {rect.id}: not found as unplaced, got {solution.find_rectangle(rect.id)}")
+
+  missing_box_id = max(solution.boxes) + 1
+  if solution.rectangles_in_box(missing_box_id) is not None:
+    violations.append(f"Box {missing_box_id} doesn't exist but has rects")
+  if solution.rectangles_in_box(UNPLACED_BOX_ID) is not None:
+    violations.append("The unplaced rects are returned as a box")
+  # Rects are numbered from 0
+  if solution.find_rectangle(rect_number) is not None:
+    violations.append(f"Rect {rect_number} doesn't exist but was found")
+  return violations
+
+def check_rotate_box() -> list[str]:
+  '''Rotating a box of tall rects packs them lying down, undoing it restores them, a square has no neighbor'''
+  violations = []
+  # Three 2x5 rects stand side by side in a 6x6 box
+  tall = [Rectangle(x, 0, 2, 5, n) for n, x in enumerate([0, 2, 4])]
+  solution = BoxSolution(6, [Box(0, 6, *tall)])
+  before = solution.solution_hash()
+  (neighbors, _) = RotateBox.get_neighbors_with_stats(solution)
+  if len(neighbors) != 1:
+    return [f"Expected one neighbor for one box, got {len(neighbors)}"]
+
+  move = neighbors[0].move
+  if not move.apply_to_solution(solution):
+    return ["The rotation of the box failed"]
+  rects = solution.rectangles_in_box(0)
+  if not solution.is_valid() or len(rects) != 3:
+    violations.append(f"The rotated box isn't a valid packing of all rects: {solution.boxes[0]}")
+  if any((r.get_width(), r.get_height()) != (5, 2) for r in rects):
+    violations.append(f"Not all rects lie down after the rotation: {solution.boxes[0]}")
+  if len({r.get_y() for r in rects}) != 3:
+    violations.append(f"The rects aren't stacked after the rotation: {solution.boxes[0]}")
+  move.undo(solution)
+  if solution.solution_hash() != before:
+    violations.append(f"Undoing the rotation doesn't restore the box: {solution.boxes[0]}")
+
+  # Nothing changes when a single square is rotated
+  (neighbors, _) = RotateBox.get_neighbors_with_stats(BoxSolution(6, [Box(0, 6, Rectangle(0, 0, 3, 3, 0))]))
+  if len(neighbors) != 0:
+    violations.append("Rotating a single square gives a neighbor")
+  return violations
+
+def check_max_additional_fit() -> list[str]:
+  '''The capacity estimate fills an empty box to its known maximum and a full box with nothing, both stay untouched'''
+  violations = []
+  cases = [
+    # Only four 3x3 squares fit into a 6x6 box
+    ("Empty box", Box(0, 6), [(3, 3)] * 5, 4),
+    ("Full box", Box(0, 6, Rectangle(0, 0, 6, 6, 0)), [(1, 1)] * 3, 0),
+    # The left half is taken, so two of the 2x2 squares fit on the right
+    ("Half filled box", Box(0, 4, Rectangle(0, 0, 2, 4, 0)), [(2, 2)] * 3, 2)
+  ]
+  for (name, box, candidates, expected) in cases:
+    before = [(r.id, r.get_x(), r.get_y(), r.get_width()) for r in box.rects.values()]
+    fits = box.max_additional_fit(candidates)
+    if fits != expected:
+      violations.append(f"{name}: {fits} of {candidates} fit, expected {expected}")
+    if [(r.id, r.get_x(), r.get_y(), r.get_width()) for r in box.rects.values()] != before:
+      violations.append(f"{name}: the estimate changed the box to {box}")
+  return violations
+
+def box_state(box: Box) -> dict:
+  '''Everything a box derives from its rects, by name, in the form the callers get it'''
+  return {
+    "sorted free coordinates": list(box.get_free_coordinates(sort=True)),
+    "free coordinates": set(box.get_free_coordinates()),
+    "skyline": box.skyline(),
+    "adjacent coordinates": set(box.get_adjacent_coordinates()),
+    "incident edge count": box.get_incident_edge_count()
+  }
+
+def check_insert_remove() -> list[str]:
+  '''Inserting a rect into a box and removing it again restores everything the box keeps track of'''
+  violations = []
+  for seed in range(20):
+    rng = random.Random(seed)
+    box = Box(0, 10)
+    for rect_id in range(8):
+      box.fit_rect_compress(Rectangle(0, 0, rng.randrange(1, 5), rng.randrange(1, 5), rect_id))
+
+    before = box_state(box)
+    if before["sorted free coordinates"] != sorted(before["free coordinates"]):
+      violations.append(f"Seed {seed}: the sorted free coordinates are out of order or out of sync")
+    rect = Rectangle(0, 0, rng.randrange(1, 4), rng.randrange(1, 4), 8)
+    if not box.fit_rect_compress(rect):
+      continue
+    box.remove_rect(rect.id)
+    after = box_state(box)
+    for name, value in before.items():
+      if after[name] != value:
+        violations.append(f"Seed {seed}: the {name} changed after inserting and removing {rect}")
+  return violations
+
+def check_compact_push() -> list[str]:
+  '''Compact pushes a floating rect flush into the closest corner, around the rects in its way'''
+  violations = []
+  cases = [
+    ("Empty box", [], (0, 0)),
+    ("Left column taken", [Rectangle(0, 0, 3, 8, 1)], (3, 0)),
+    # Pushing left first is blocked, only after pushing down the way to the left is free
+    ("Top left corner taken", [Rectangle(0, 4, 3, 4, 1)], (0, 0))
+  ]
+  for (name, blocking, corner) in cases:
+    # Solutions remember a quarter of their rects as last moved, the other box makes sure there is room for one
+    floating = Rectangle(5, 5, 2, 2, 0)
+    fillers = [Rectangle(x, 0, 1, 1, 10 + x) for x in range(4)]
+    solution = BoxSolution(8, [Box(0, 8, floating, *blocking), Box(1, 8, *fillers)])
+    (neighbors, _) = Compact.get_neighbors_with_stats(solution)
+    moves = [n.move for n in neighbors if n.move.rect_id == floating.id]
+    if not any((m.new_x, m.new_y) == corner for m in moves):
+      violations.append(f"{name}: no push of the floating rect to {corner}, got {[(m.new_x, m.new_y) for m in moves]}")
+      continue
+    next(m for m in moves if (m.new_x, m.new_y) == corner).apply_to_solution(solution)
+    if (floating.get_x(), floating.get_y()) != corner or not solution.is_valid():
+      violations.append(f"{name}: the push left the solution at {solution.boxes[0]}")
+  return violations
+
+def float_rects(solution: BoxSolution) -> BoxSolution:
+  '''Moves the rect of every box of a trivial solution into the far corner, so each neighborhood has moves to make'''
+  for box in solution.boxes.values():
+    rect = box.remove_rect(next(iter(box.rects)))
+    rect.move_to(solution.side_length - rect.get_width(), solution.side_length - rect.get_height())
+    box.add_rect(rect)
+  return solution
+
+def check_materialized_neighbors() -> list[str]:
+  '''The neighbors as solutions are the start solution with each move applied, with the score of the move'''
+  violations = []
+  problem = BoxProblem(box_length=8, n_rect=12, w_range=range(2, 6), h_range=range(2, 5), seed=1)
+  start = float_rects(problem.current_solution)
+
+  for neighborhood in [Geometric, Compact, DissolveBox, RotateBox, CoarseToFine]:
+    # Which moves are generated depends on the order of the rects, so both paths start from identical copies
+    solution = deepcopy(start)
+
+    expected = []
+    for (move, score) in neighborhood.get_neighbors(deepcopy(start)):
+      neighbor = deepcopy(start)
+      if move.apply_to_solution(neighbor):
+        expected.append((neighbor.solution_hash(), score))
+    materialized = neighborhood.get_neighbor_solutions(solution)
+
+    name = neighborhood.__name__
+    if len(expected) == 0:
+      violations.append(f"{name}: no neighbors to compare")
+    if [(n.solution_hash(), score) for (n, score) in materialized] != expected:
+      violations.append(f"{name}: the materialized neighbors differ from the moves applied to the start solution")
+    for (neighbor, score) in materialized:
+      if neighborhood.generate_heuristic(neighbor) != score:
+        violations.append(f"{name}: a neighbor scores {neighborhood.generate_heuristic(neighbor)} instead of {score}")
+    if solution.solution_hash() != start.solution_hash():
+      violations.append(f"{name}: materializing the neighbors changed the solution")
+  return violations
+
+def check_progress_counters() -> list[str]:
+  '''The ticks since the last improvement drop to zero exactly on the ticks that strictly beat the best score'''
+  violations = []
+  improvements = 0
+  for algorithm_type in [LocalSearch, SimulatedAnnealing]:
+    random.seed(0)
+    problem = BoxProblem(box_length=8, n_rect=15, w_range=range(2, 6), h_range=range(2, 6), seed=0)
+    algorithm = algorithm_type(problem, Geometric)
+    algorithm.record_progress()
+    best = algorithm.problem.current_solution.get_heuristic_score()
+    for tick in range(60):
+      (since, improving) = (algorithm.progress.ticks_since_improvement, algorithm.progress.improving_ticks)
+      algorithm.tick()
+      algorithm.record_progress()
+      score = algorithm.problem.current_solution.get_heuristic_score()
+      name = f"{algorithm_type.__name__}, tick {tick}"
+      if score < best:
+        best = score
+        improvements += 1
+        if (algorithm.progress.ticks_since_improvement, algorithm.progress.improving_ticks) != (0, improving + 1):
+          violations.append(f"{name}: improved to {score}, but the counters are at {algorithm.progress}")
+      elif (algorithm.progress.ticks_since_improvement, algorithm.progress.improving_ticks) != (since + 1, improving):
+        violations.append(f"{name}: didn't improve, but the counters are at {algorithm.progress}")
+  if improvements == 0:
+    violations.append("No tick improved, the check didn't test the reset")
+  return violations
+
+def check_annealing_rejects_worse() -> list[str]:
+  '''Close to zero temperature, the simulated annealing never accepts a strictly worse neighbor'''
+  violations = []
+  rejected = 0
+  random.seed(0)
+  problem = BoxProblem(box_length=8, n_rect=15, w_range=range(2, 6), h_range=range(2, 6), seed=0)
+  algorithm = SimulatedAnnealing(problem, Geometric)
+  algorithm.temperature = 1e-9
+  decisions = []
+  algorithm.add_acceptance_observer(decisions.append)
+  for tick in range(60):
+    solution = algorithm.problem.current_solution
+    # Scoring the neighbors reorders the rects, so only their placement is compared
+    before = solution.solution_hash()
+    decisions.clear()
+    algorithm.tick()
+    if len(decisions) == 0 or not decisions[0].current_score < decisions[0].candidate_score:
+      continue
+    rejected += 1
+    if decisions[0].accepted or solution.solution_hash() != before:
+      violations.append(
+        f"Tick {tick}: accepted {decisions[0].candidate_score} over {decisions[0].current_score} "
+        f"with a probability of {decisions[0].threshold}"
+      )
+  if rejected == 0:
+    violations.append("No neighbor was worse, the check didn't test anything")
+  return violations
+
+def check_max_boxes() -> list[str]:
+  '''With a box limit, no neighbor of any neighborhood opens a box beyond it'''
+  violations = []
+  for neighborhood in [Geometric, Compact, DissolveBox, RotateBox, CoarseToFine, Knapsack, Permutation]:
+    # A packing that reaches the limit right away, with some rects that don't fit in at all,
+    # and one floating rect per box with the limit set to that box count
+    packed = BoxProblem(box_length=8, n_rect=20, w_range=range(2, 6), h_range=range(2, 6), seed=0, max_boxes=3)
+    floating = BoxProblem(box_length=8, n_rect=8, w_range=range(2, 6), h_range=range(2, 6), seed=0)
+    float_rects(floating.current_solution).max_boxes = 8
+    neighbor_count = 0
+    for problem in [packed, floating]:
+      max_boxes = problem.current_solution.max_boxes
+      solution = neighborhood.initialize(problem.current_solution)
+      for (neighbor, _) in neighborhood.get_neighbor_solutions(solution):
+        neighbor_count += 1
+        if len(neighbor.boxes) > max_boxes or not neighbor.is_valid():
+          violations.append(f"{neighborhood.__name__}: a neighbor uses {len(neighbor.boxes)} of {max_boxes} boxes")
+    if neighbor_count == 0:
+      violations.append(f"{neighborhood.__name__}: no neighbors to check")
+  return violations
+
+def check_problem_builder() -> list[str]:
+  '''The builder generates the same instance as the constructor and rejects parameters that don't go together'''
+  def builder() -> ProblemBuilder:
+    return ProblemBuilder().box_length(10).rect_count(20).widths(range(1, 4)).heights(range(5, 8)).seed(3)
+
+  violations = []
+  problem = builder().grid_unit(2).max_boxes(4).build()
+  expected = BoxProblem(
+    box_length=10, n_rect=20, w_range=range(1, 4), h_range=range(5, 8), seed=3, grid_unit=2, max_boxes=4
+  )
+  if json.dumps(problem.to_dict()) != json.dumps(expected.to_dict()):
+    violations.append("The built instance differs from the one of the constructor")
+  # Packing into the limited boxes may flip rects, but their original sizes must come from the right ranges
+  solution = problem.current_solution
+  rects = [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values())
+  sizes = [(r.height, r.width) if r.flipped else (r.width, r.height) for r in rects]
+  if len(rects) != 20 or any(w not in range(1, 4) or h not in range(5, 8) for (w, h) in sizes):
+    violations.append(f"The built rects don't have their sizes from the given ranges: {sizes}")
+
+  rejected = {
+    "no box length": ProblemBuilder().rect_count(20).widths(range(1, 4)).heights(range(5, 8)),
+    "no height range": ProblemBuilder().box_length(10).rect_count(20).widths(range(1, 4)),
+    "box length 0": builder().box_length(0),
+    "negative rect count": builder().rect_count(-1),
+    "empty width range": builder().widths(range(4, 4)),
+    "zero height": builder().heights(range(0, 3)),
+    "empty value range": builder().values(range(5, 1)),
+    "rects larger than the box": builder().widths(range(11, 14)),
+    "grid unit 0": builder().grid_unit(0),
+    "no boxes": builder().max_boxes(0)
+  }
+  for name, rejected_builder in rejected.items():
+    try:
+      rejected_builder.build()
+      violations.append(f"Built an instance with {name}")
+    except ValueError:
+      pass
+  return violations
+
+def full_box_solution() -> BoxSolution:
+  '''A 2x2 box filled with four 1x1 rects, the only feasible move for each of them is into a new box'''
+  rects = [Rectangle(x, y, 1, 1, i) for (i, (x, y)) in enumerate([(0, 0), (1, 0), (0, 1), (1, 1)])]
+  return BoxSolution(2, [Box(0, 2, *rects)])
+
+def check_candidate_counts() -> list[str]:
+  '''The geometric neighborhood counts its candidates on a full box as worked out by hand, per tick of a run'''
+  violations = []
+  # Every rect is tried at the 3x3 corners of the grid, 5 of which let a 1x1 rect stick out of the box.
+  # Of the other 4, one is where the rect already is and isn't a candidate, the other 3 overlap a rect.
+  # Each rect adds the feasible move into a new box, so that's 4 * (5 + 3 + 1) = 36 with 4 feasible.
+  (neighbors, stats) = Geometric.get_neighbors_with_stats(full_box_solution())
+  if (stats.generated, stats.feasible, len(neighbors)) != (36, 4, 4):
+    violations.append(
+      f"Expected 4/36 candidates feasible with 4 neighbors, got {stats} with {len(neighbors)} neighbors"
+    )
+
+  # Opening a box only makes it worse, so every tick of the run enumerates the same candidates again
+  problem = BoxProblem(box_length=2, n_rect=4, w_range=range(1, 2), h_range=range(1, 2), seed=0)
+  algorithm = LocalSearch(problem, Geometric)
+  problem.current_solution = full_box_solution()
+  for tick in range(1, 4):
+    algorithm.tick()
+    if (algorithm.candidate_stats.generated, algorithm.candidate_stats.feasible) != (36 * tick, 4 * tick):
+      violations.append(
+        f"After {tick} ticks, expected {4 * tick}/{36 * tick} feasible, got {algorithm.candidate_stats}"
+      )
+  return violations
+
+def check_shuffle() -> list[str]:
+  '''Shuffles with different seeds start from different valid solutions without more boxes, the same seed repeats'''
+  violations = []
+  for (name, initialize) in [("trivial", lambda s: s), ("packed", Permutation.initialize)]:
+    def shuffled(seed: int) -> BoxSolution:
+      problem = BoxProblem(box_length=8, n_rect=15, w_range=range(2, 6), h_range=range(2, 6), seed=0)
+      problem.current_solution = initialize(problem.current_solution)
+      box_count = len(problem.current_solution.boxes)
+      problem.shuffle_into_random_feasible(seed)
+      solution = problem.current_solution
+      if not solution.is_valid() or len(solution.unplaced_rects) > 0:
+        violations.append(f"{name}, seed {seed}: the shuffled solution isn't valid or leaves rects unplaced")
+      if len(solution.boxes) > box_count:
+        violations.append(f"{name}, seed {seed}: the shuffle went from {box_count} to {len(solution.boxes)} boxes")
+      return solution
+
+    hashes = [shuffled(seed).solution_hash() for seed in range(6)]
+    if len(set(hashes)) != len(hashes):
+      violations.append(f"{name}: only {len(set(hashes))} different solutions for {len(hashes)} seeds")
+    if shuffled(3).solution_hash() != hashes[3]:
+      violations.append(f"{name}: shuffling with the same seed gave another solution")
+  return violations
+
+class PausingLocalSearch(LocalSearch):
+  '''Local search that waits in the middle of every tick until it is resumed, and records the neighborhood it used'''
+  in_tick: threading.Event
+  resume: threading.Event
+  strategies: list[tuple[type, type]]
+  '''Neighborhood at the start and at the end of every tick'''
+
+  def __init__(self, problem, neighborhood_definition=Geometric):
+    super().__init__(problem, neighborhood_definition)
+    self.in_tick = threading.Event()
+    self.resume = threading.Event()
+    self.strategies = []
+
+  def tick(self):
+    strategy = self.strategy
+    self.in_tick.set()
+    self.resume.wait()
+    self.resume.clear()
+    super().tick()
+    self.strategies.append((strategy, self.strategy))
+
+def check_strategy_between_ticks() -> list[str]:
+  '''A neighborhood picked while a tick runs is only used from the next tick on'''
+  problem = BoxProblem(box_length=8, n_rect=10, w_range=range(2, 6), h_range=range(2, 6), seed=0)
+  algorithm = PausingLocalSearch(problem, Geometric)
+
+  def job(worker: SearchWorker) -> str:
+    for _ in range(2):
+      algorithm.tick()
+      worker.after_tick()
+    return ""
+
+  worker = SearchWorker(lambda message: None)
+  worker.start(algorithm, job)
+  algorithm.in_tick.wait()
+  algorithm.in_tick.clear()
+  worker.between_ticks(lambda: algorithm.set_strategy(Compact))
+  algorithm.resume.set()
+  algorithm.in_tick.wait()
+  algorithm.resume.set()
+  worker.stop(timeout=10)
+
+  violations = []
+  if algorithm.strategies != [(Geometric, Geometric), (Compact, Compact)]:
+    names = [(start.__name__, end.__name__) for (start, end) in algorithm.strategies]
+    violations.append(f"Expected the first tick on Geometric and the second on Compact, got {names}")
+
+  # Without a running job, the change is made right away
+  worker.between_ticks(lambda: algorithm.set_strategy(RotateBox))
+  if algorithm.strategy != RotateBox:
+    violations.append(f"The change without a running job wasn't applied, still on {algorithm.strategy.__name__}")
+  return violations
+
+CHECKS: dict[str, Callable[[], list[str]]] = {
+  "incremental_coordinates": check_incremental_coordinates,
+  "edge_flush": check_edge_flush,
+  "compound_rollback": check_compound_rollback,
+  "lower_bound_guard": check_lower_bound_guard,
+  "local_optimum_kept": check_local_optimum_kept,
+  "rect_lookups": check_rect_lookups,
+  "rotate_box": check_rotate_box,
+  "max_additional_fit": check_max_additional_fit,
+  "insert_remove": check_insert_remove,
+  "compact_push": check_compact_push,
+  "materialized_neighbors": check_materialized_neighbors,
+  "progress_counters": check_progress_counters,
+  "annealing_rejects_worse": check_annealing_rejects_worse,
+  "max_boxes": check_max_boxes,
+  "problem_builder": check_problem_builder,
+  "candidate_counts": check_candidate_counts,
+  "shuffle": check_shuffle,
+  "strategy_between_ticks": check_strategy_between_ticks
+}
+'''Every check by name, each returns the violations it found'''
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--check",
+    type=str,
+    action="append",
+    help=f"Only run this check, can be given more than once. Possible values: {list(CHECKS)}"
+  )
+
+  args = parser.parse_args()
+
+  # Per check (Check / Violations)
+  table = Table("Check", "Violations")
+  failures = []
+  for name in args.check or CHECKS:
+    violations = CHECKS[name]()
+    failures += [f"{name}: {v}" for v in violations]
+    table.add_row(name, str(len(violations)))
+
+  console = Console()
+  console.print(table)
+  if len(failures) > 0:
+    sys.exit("\n".join(failures))
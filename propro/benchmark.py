@@ -11,6 +11,7 @@ from collections import deque
 from rich.table import Table
 from rich.console import Console
 
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
 from algorithms import OptimizationAlgorithm
 from modes import get_available_modes
 from problem.box_problem.box_problem import BoxProblem
@@ -20,47 +21,19 @@ from problem.box_problem.box_problem import BoxProblem
 if __name__ == "__main__":
   parser = ArgumentParser()
 
-  parser.add_argument(
-    "--rect-number",
-    type=int,
-    help="Just a number",
-    default=200
-  )
+  add_problem_args(parser, rect_number=200, rect_size="1-10", box_length=15)
   parser.add_argument(
     '--tick-number',
     type=int,
     help="Number of ticks to run the algorithms for",
     default=400
   )
-  parser.add_argument(
-    "--rect-x",
-    type=str,
-    help="Min-max range (e.g. 5-12)",
-    default="1-10"
-  )
-  parser.add_argument(
-    "--rect-y",
-    type=str,
-    help="Min-max range (e.g. 5-12)",
-    default="1-10"
-  )
-  parser.add_argument(
-    "--box-length",
-    type=int,
-    help="Just a number",
-    default=15
-  )
   parser.add_argument(
     "--seed",
     type=int,
     help="RNG seed"
   )
-  parser.add_argument(
-    "--log",
-    type=str,
-    help="Log level can be one of ['DEBUG', 'INFO', 'WARNING', 'ERROR', 'CRITICAL']",
-    default="WARNING"
-  )
+  add_log_args(parser)
 
   args = parser.parse_args()
 
@@ -68,11 +41,7 @@ if __name__ == "__main__":
   if args.seed is not None:
     random.seed(args.seed)
 
-  # Set log level
-  numeric_level = getattr(logging, args.log.upper(), None)
-  if not isinstance(numeric_level, int):
-    raise ValueError(f"Invalid log level: {args.log}")
-  logging.basicConfig(level=numeric_level)
+  configure_logging(args)
 
   # Keep track of results for all variations
   # (Algo / Mode / Time / Score)
@@ -88,10 +57,11 @@ if __name__ == "__main__":
       optimization_problem = BoxProblem(
         box_length=args.box_length,
         n_rect=args.rect_number,
-        w_range=range(*[int(i) for i in args.rect_x.split("-")]),
-        h_range=range(*[int(i) for i in args.rect_x.split("-")])
+        w_range=parse_range(args.rect_x),
+        h_range=parse_range(args.rect_y)
       )
       optimization_algorithm = Algorithm(optimization_problem, Mode)
+      optimization_algorithm.record_progress()
 
       # Initialize circular buffer in case the algorithm converges before # ticks is reached
       last_scores = deque(maxlen=5)
@@ -101,6 +71,8 @@ if __name__ == "__main__":
       for i in range(args.tick_number):
         logging.info(f"Iteration {i}")
         optimization_algorithm.tick()
+        optimization_algorithm.check_lower_bound()
+        optimization_algorithm.record_progress()
         last_scores.append(optimization_algorithm.problem.current_solution.get_heuristic_score())
         # Break loop if algortihm has been stagnant for the last maxlen iterations
         if last_scores.count(last_scores[0]) == last_scores.maxlen:
@@ -112,16 +84,26 @@ if __name__ == "__main__":
         Algorithm.__name__,
         Mode.__name__,
         stop_time - start_time,
-        optimization_algorithm.problem.current_solution.get_heuristic_score()
+        optimization_algorithm.problem.current_solution.get_heuristic_score(),
+        optimization_algorithm.problem.current_solution.fill_stats(),
+        optimization_algorithm.progress
       ))
 
       logging.info(f"Finished in {stop_time - start_time :0.6f} seconds")
       logging.info(f"Score: {optimization_algorithm.problem.current_solution.get_heuristic_score().box_count}")
 
   # Print results
-  table = Table("Algorithm", "Mode", "Time (s)", "Score (#Boxes)")
-  for (algo, mode, t, score) in results:
-    table.add_row(algo, mode, f"{t:0.6f}", str(score.box_count))
+  table = Table("Algorithm", "Mode", "Time (s)", "Score (#Boxes)", "Fill mean", "Fill stddev", "Improving ticks")
+  for (algo, mode, t, score, fill, progress) in results:
+    table.add_row(
+      algo,
+      mode,
+      f"{t:0.6f}",
+      str(score.box_count),
+      f"{fill.mean:.1%}",
+      f"{fill.stddev:.1%}",
+      str(progress.improving_ticks)
+    )
 
   console = Console()
   console.print(table)
@@ -0,0 +1,94 @@
+'''
+Checkpointing of the best solution during long running searches.
+'''
+
+import json
+import logging
+import os
+import tempfile
+import time
+from copy import deepcopy
+from typing import Optional
+
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_heuristic import GenericHeuristic
+
+logger = logging.getLogger(__name__)
+
+def write_solution(solution: BoxSolution, path: str):
+  '''
+  Atomically writes a solution as JSON to the given path, together with its fill statistics for reference.
+  The file is written to a temporary file next to the target first and then renamed,
+  so a crash while writing will never leave a half-written file behind.
+  '''
+  data = solution.to_dict()
+  # Only informative, reading the solution back ignores them
+  data["fill_stats"] = vars(solution.fill_stats())
+  directory = os.path.dirname(os.path.abspath(path))
+  fd, tmp_path = tempfile.mkstemp(dir=directory, prefix=".", suffix=".tmp")
+  try:
+    with os.fdopen(fd, "w", encoding="utf-8") as f:
+      json.dump(data, f)
+    os.replace(tmp_path, path)
+  except BaseException:
+    os.remove(tmp_path)
+    raise
+
+def read_solution(path: str) -> BoxSolution:
+  '''Reads a solution from a JSON file as written by `write_solution`'''
+  with open(path, "r", encoding="utf-8") as f:
+    return BoxSolution.from_dict(json.load(f))
+
+class BestSolutionCheckpoint:
+  '''
+  Keeps track of the best solution seen so far and writes it to disk whenever it improves.
+  Writes are throttled to at most one every `min_interval` seconds, an improvement in between
+  is held back until the next write is due or `flush` is called.
+  '''
+  path: Optional[str]
+  '''Path to write the best solution to, `None` to only keep track of it'''
+  min_interval: float
+  '''Minimum number of seconds between two writes'''
+  best_solution: Optional[BoxSolution]
+  '''Copy of the best solution seen so far'''
+  best_score: Optional[GenericHeuristic]
+  '''Score of the best solution seen so far'''
+
+  __last_write: float
+  __pending: bool
+
+  def __init__(self, path: Optional[str] = None, min_interval: float = 1.0):
+    self.path = path
+    self.min_interval = min_interval
+    self.best_solution = None
+    self.best_score = None
+    self.__last_write = float("-inf")
+    self.__pending = False
+
+  def update(self, solution: BoxSolution) -> bool:
+    '''Checks the given solution against the best one so far. Returns true if it was an improvement.'''
+    score = solution.get_heuristic_score()
+    if not score.is_valid():
+      return False
+    if self.best_score is not None and not score < self.best_score:
+      return False
+
+    # Store a copy, since algorithms keep modifying their solution in-place.
+    # It is canonicalized so the same packing is always written the same way.
+    self.best_solution = deepcopy(solution)
+    self.best_solution.canonicalize()
+    self.best_score = score
+    self.__pending = True
+
+    if time.monotonic() - self.__last_write >= self.min_interval:
+      self.flush()
+    return True
+
+  def flush(self):
+    '''Writes the best solution to disk if there is an improvement that was not written yet'''
+    if not self.__pending or self.path is None:
+      return
+    write_solution(self.best_solution, self.path)
+    self.__last_write = time.monotonic()
+    self.__pending = False
+    logger.info("Checkpointed solution with score %s to %s", self.best_score, self.path)
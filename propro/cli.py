@@ -0,0 +1,65 @@
+'''
+Command line options shared by the entry points, so they are named, explained and parsed the same way everywhere.
+'''
+
+import logging
+from argparse import ArgumentParser, Namespace
+from typing import Optional
+
+LOG_LEVELS = ['DEBUG', 'INFO', 'WARNING', 'ERROR', 'CRITICAL']
+
+def add_problem_args(
+    parser: ArgumentParser,
+    rect_number: Optional[int] = None,
+    rect_size: Optional[str] = None,
+    box_length: Optional[int] = None,
+    rect_number_help: str = "Number of rects of a generated instance"
+  ):
+  '''
+  Adds the options that describe a generated instance, with the defaults of the calling script.
+  Options without a default stay `None` if they are not given, e.g. to ask for them in the gui.
+  '''
+  parser.add_argument(
+    "--rect-number",
+    type=int,
+    help=rect_number_help,
+    default=rect_number
+  )
+  parser.add_argument(
+    "--rect-x",
+    type=str,
+    help="Widths of the rects as a min-max range, the max is excluded (e.g. 5-12)",
+    default=rect_size
+  )
+  parser.add_argument(
+    "--rect-y",
+    type=str,
+    help="Heights of the rects as a min-max range, the max is excluded (e.g. 5-12)",
+    default=rect_size
+  )
+  parser.add_argument(
+    "--box-length",
+    type=int,
+    help="Side length of the square boxes",
+    default=box_length
+  )
+
+def parse_range(text: str) -> range:
+  '''Parses a min-max option like `--rect-x` into the range of sizes it stands for'''
+  return range(*[int(i) for i in text.split("-")])
+
+def add_log_args(parser: ArgumentParser):
+  '''Adds the option for the log level, see `configure_logging`'''
+  parser.add_argument(
+    "--log",
+    type=str,
+    help=f"Log level can be one of {LOG_LEVELS}",
+    default="WARNING"
+  )
+
+def configure_logging(args: Namespace):
+  '''Sets up logging with the level given by `--log`'''
+  numeric_level = getattr(logging, args.log.upper(), None)
+  if not isinstance(numeric_level, int):
+    raise ValueError(f"Invalid log level: {args.log}")
+  logging.basicConfig(level=numeric_level)
@@ -8,7 +8,7 @@ from typing import Optional
 
 import FreeSimpleGUI as sg
 
-from algorithms import OptimizationAlgorithm, LocalSearch, get_algo_by_name
+from algorithms import OptimizationAlgorithm, LocalSearch, TieBreak, get_algo_by_name
 from modes import Mode, get_available_modes, get_mode_by_name
 
 @dataclass
@@ -25,6 +25,18 @@ class RunConfiguration:
   rect_x_size: range
   rect_y_size: range
   box_length: int
+  seed: Optional[int] = None
+  '''Seed to generate the instance with, a random one is picked if not set'''
+  problem_dir: Optional[str] = None
+  '''Directory of JSON or 2DPackLib problem instances to cycle through instead of generating one'''
+
+  # Run specific
+  target_box_count: Optional[int] = None
+  '''Stop the run as soon as a solution with at most this many boxes is found'''
+  log_acceptance: bool = False
+  '''Show a log of every acceptance decision the algorithm makes'''
+  tie_break: TieBreak = TieBreak.RANDOM
+  '''How the local search decides between neighbors with the same score'''
 
 def show_config_picker(
     algo_default: Optional[str] = None,
@@ -32,7 +44,12 @@ def show_config_picker(
     rect_number_default: Optional[int] = None,
     rect_x_default: Optional[range] = None,
     rect_y_default: Optional[range] = None,
-    box_len_default: Optional[int] = None
+    box_len_default: Optional[int] = None,
+    target_boxes_default: Optional[int] = None,
+    log_acceptance_default: bool = False,
+    seed_default: Optional[int] = None,
+    problem_dir_default: Optional[str] = None,
+    tie_break_default: TieBreak = TieBreak.RANDOM
   ) -> RunConfiguration:
   '''Shows a dialogue for the user to pick config values. Accepts optional default values'''
   # Try and parse default mode for correct pre-selection
@@ -82,13 +99,39 @@ def show_config_picker(
     [
       sg.Text("Box length"),
       sg.Input(f"{box_len_default if box_len_default else 10}", k="box_len", size=3)
+    ],
+    [
+      sg.Text("Seed (optional)"),
+      sg.Input(f"{seed_default if seed_default is not None else ''}", k="seed", size=10)
+    ],
+    [
+      sg.Text("Problem directory (optional)"),
+      sg.Input(f"{problem_dir_default if problem_dir_default else ''}", k="problem_dir", size=20),
+      sg.FolderBrowse()
     ]
   ]
   problem_frame = sg.Frame("Optimization Problem", layout=problem_layout)
 
+  # Frame for the run specific options
+  run_layout = [
+    [
+      sg.Text("Target box count (optional)"),
+      sg.Input(f"{target_boxes_default if target_boxes_default else ''}", k="target_boxes", size=3)
+    ],
+    [
+      sg.Checkbox("Log acceptance decisions", default=log_acceptance_default, k="log_acceptance")
+    ],
+    [
+      sg.Text("Tie break (local search)"),
+      sg.Combo([t.value for t in TieBreak], default_value=tie_break_default.value, k="tie_break", readonly=True)
+    ]
+  ]
+  run_frame = sg.Frame("Run", layout=run_layout)
+
   layout = [
     [solver_frame],
     [problem_frame],
+    [run_frame],
     [sg.Button("Ok")]
   ]
 
@@ -119,7 +162,12 @@ def show_config_picker(
           int(values["n_rect"]),
           range(int(values["x_min"]), int(values["x_max"])),
           range(int(values["y_min"]), int(values["y_max"])),
-          int(values["box_len"])
+          int(values["box_len"]),
+          int(values["seed"]) if values["seed"] else None,
+          values["problem_dir"] if values["problem_dir"] else None,
+          int(values["target_boxes"]) if values["target_boxes"] else None,
+          values["log_acceptance"],
+          TieBreak(values["tie_break"])
         )
         window.close()
         return config
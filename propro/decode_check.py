@@ -0,0 +1,104 @@
+'''
+Property check of the permutation decoding on random orderings of random instances,
+to pin down the contract that the permutation neighborhood and the algorithms on top of it rely on.
+'''
+
+import random
+import sys
+from argparse import ArgumentParser
+
+from rich.table import Table
+from rich.console import Console
+
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
+from modes.neighborhoods.permutation import Permutation, EncodedSolution
+from problem.box_problem.box_problem import BoxProblem
+
+def layout(encoded: EncodedSolution) -> dict[int, tuple[int, int, int, int, int]]:
+  '''Box, position and size of every rect after decoding, to tell two decodings apart'''
+  return { r.id: (r.box_id, r.get_x(), r.get_y(), r.get_width(), r.get_height()) for r in encoded.rects }
+
+def decode_violations(encoded: EncodedSolution) -> list[str]:
+  '''Decodes the ordering in place and returns how the result breaks the decode contract'''
+  try:
+    boxes = Permutation.decode_solution(encoded)
+  # In debug mode the decoding checks itself and stops at the first violation
+  except AssertionError as e:
+    return [str(e)]
+  return Permutation.decode_violations(encoded.rects, boxes, encoded.side_length, encoded.max_boxes)
+
+def check_ordering(encoded: EncodedSolution) -> tuple[list[str], int]:
+  '''
+  Decodes the ordering and every adjacent swap of it.
+  Returns the violations of the decode contract and the number of swaps that decoded into the same layout,
+  i.e. swaps that aren't true neighbors.
+  '''
+  violations = []
+  unchanged = 0
+  original = encoded.copy()
+  violations += decode_violations(original)
+  original_layout = layout(original)
+
+  for i in range(len(encoded.rects) - 1):
+    swapped = encoded.copy()
+    swapped.rects[i], swapped.rects[i + 1] = swapped.rects[i + 1], swapped.rects[i]
+    violations += decode_violations(swapped)
+    if layout(swapped) == original_layout:
+      unchanged += 1
+  return violations, unchanged
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--instances",
+    type=int,
+    help="Number of random instances to check",
+    default=10
+  )
+  parser.add_argument(
+    "--orderings",
+    type=int,
+    help="Number of random orderings to decode per instance",
+    default=10
+  )
+  add_problem_args(parser, rect_number=20, rect_size="1-10", box_length=15)
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="Seed of the first instance, the following ones count up from it",
+    default=0
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  configure_logging(args)
+
+  # Per instance (Seed / Decodings / Violations / Unchanged swaps)
+  table = Table("Seed", "Decodings", "Violations", "Unchanged swaps")
+  failures = []
+  for seed in range(args.seed, args.seed + args.instances):
+    problem = BoxProblem(
+      box_length=args.box_length,
+      n_rect=args.rect_number,
+      w_range=parse_range(args.rect_x),
+      h_range=parse_range(args.rect_y),
+      seed=seed
+    )
+    encoded = Permutation.encode_solution(problem.current_solution)
+    rng = random.Random(seed)
+    violations = []
+    unchanged = 0
+    for _ in range(args.orderings):
+      rng.shuffle(encoded.rects)
+      (ordering_violations, ordering_unchanged) = check_ordering(encoded)
+      violations += ordering_violations
+      unchanged += ordering_unchanged
+    failures += [f"Seed {seed}: {v}" for v in violations]
+    table.add_row(str(seed), str(args.orderings * len(encoded.rects)), str(len(violations)), str(unchanged))
+
+  console = Console()
+  console.print(table)
+  if len(failures) > 0:
+    sys.exit("\n".join(failures))
@@ -0,0 +1,117 @@
+'''
+Compares the neighborhood definitions on one and the same solution.
+'''
+
+import logging
+import random
+import time
+from argparse import ArgumentParser
+from copy import deepcopy
+
+from rich.table import Table
+from rich.console import Console
+
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
+from algorithms import get_algo_by_name
+from modes import Neighborhood, get_mode_by_name
+from problem.box_problem.box_problem import BoxProblem
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  add_problem_args(parser, rect_number=50, rect_size="1-10", box_length=15)
+  parser.add_argument(
+    "--warmup-algorithm",
+    type=str,
+    help="Algorithm to produce the solution the neighborhoods are compared on",
+    default="LocalSearch"
+  )
+  parser.add_argument(
+    "--warmup-mode",
+    type=str,
+    help="Mode of the warmup algorithm",
+    default="Geometric"
+  )
+  parser.add_argument(
+    "--warmup-ticks",
+    type=int,
+    help="Number of ticks to run the warmup algorithm for, 0 compares on the trivial solution",
+    default=0
+  )
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="RNG seed"
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  # Fix seed for deterministic problem generation if specified
+  if args.seed is not None:
+    random.seed(args.seed)
+
+  configure_logging(args)
+
+  # Initialize problem
+  optimization_problem = BoxProblem(
+    box_length=args.box_length,
+    n_rect=args.rect_number,
+    w_range=parse_range(args.rect_x),
+    h_range=parse_range(args.rect_y)
+  )
+
+  # Optionally advance the solution to something more interesting than the trivial one
+  if args.warmup_ticks > 0:
+    Algorithm = get_algo_by_name(args.warmup_algorithm)
+    Mode = get_mode_by_name(Algorithm, args.warmup_mode)
+    if Algorithm is None or Mode is None:
+      raise ValueError(f"Unknown warmup configuration: {args.warmup_algorithm} / {args.warmup_mode}")
+    warmup_algorithm = Algorithm(optimization_problem, Mode)
+    for _ in range(args.warmup_ticks):
+      warmup_algorithm.tick()
+
+  start_solution = optimization_problem.current_solution
+  start_box_count = start_solution.get_heuristic_score().box_count
+  logging.info(f"Comparing neighborhoods on a solution with {start_box_count} boxes")
+
+  # Keep track of results for all neighborhoods
+  # (Neighborhood / Size / Feasible candidates / Best score / Box delta / Time)
+  results = []
+
+  for NeighborhoodType in Neighborhood.__subclasses__():
+    logging.info(f"Generating neighbors with {NeighborhoodType.__name__}")
+
+    # Every neighborhood gets its own copy since generating neighbors may alter the solution
+    solution = deepcopy(start_solution)
+
+    start_time = time.perf_counter()
+    (neighbors, stats) = NeighborhoodType.get_neighbors_with_stats(solution)
+    stop_time = time.perf_counter()
+
+    if len(neighbors) == 0:
+      results.append((NeighborhoodType.__name__, 0, stats, "-", "-", stop_time - start_time))
+      continue
+
+    # Apply the best move according to the neighborhood's own heuristic
+    # and see what it does to the box count
+    best_neighbor = min(neighbors, key=lambda n: n.score)
+    best_neighbor.move.apply_to_solution(solution)
+    box_delta = solution.get_heuristic_score().box_count - start_box_count
+
+    results.append((
+      NeighborhoodType.__name__,
+      len(neighbors),
+      stats,
+      best_neighbor.score,
+      box_delta,
+      stop_time - start_time
+    ))
+
+  # Print results
+  table = Table("Neighborhood", "Size", "Feasible candidates", "Best score", "Box delta", "Time (s)")
+  for (name, size, stats, score, delta, t) in results:
+    table.add_row(name, str(size), str(stats), str(score), str(delta), f"{t:0.6f}")
+
+  console = Console()
+  console.print(table)
@@ -0,0 +1,127 @@
+'''
+Reading and writing problem instances as JSON files.
+'''
+
+import json
+import logging
+import os
+from argparse import ArgumentParser
+
+from cli import add_problem_args, parse_range
+from problem.box_problem.box_problem import BoxProblem
+
+logger = logging.getLogger(__name__)
+
+def write_problem(problem: BoxProblem, path: str):
+  '''Writes the rects of a problem instance as JSON to the given path'''
+  with open(path, "w", encoding="utf-8") as f:
+    json.dump(problem.to_dict(), f)
+
+PACKLIB_EXTENSION = ".ins2D"
+'''Extension of instance files in the format of 2DPackLib, all other files are read as JSON'''
+
+def read_problem(path: str) -> BoxProblem:
+  '''Reads a problem instance from a JSON file as written by `write_problem`, or from a 2DPackLib instance file'''
+  with open(path, "r", encoding="utf-8") as f:
+    if path.endswith(PACKLIB_EXTENSION):
+      return BoxProblem.parse_2dpacklib(f.read())
+    return BoxProblem.from_dict(json.load(f))
+
+def read_problem_directory(directory: str) -> list[tuple[str, BoxProblem]]:
+  '''
+  Reads all JSON and 2DPackLib problem instances in a directory, sorted by file name.
+  Files that can't be read are skipped with a warning.
+  Returns a list of `(file name, problem)` tuples.
+  '''
+  problems = []
+  for file_name in sorted(os.listdir(directory)):
+    if not file_name.endswith((".json", PACKLIB_EXTENSION)):
+      continue
+    try:
+      problems.append((file_name, read_problem(os.path.join(directory, file_name))))
+    #pylint: disable=W0718
+    except Exception as e:
+      logger.warning("Skipping malformed problem file %s: %s", file_name, e)
+
+  if len(problems) == 0:
+    logger.warning("No problem files found in %s", directory)
+  return problems
+
+def difficulty_ramp(
+    box_length: int,
+    num_boxes: int,
+    count: int,
+    tightness_range: tuple[float, float],
+    seed: int = 0
+  ) -> list[BoxProblem]:
+  '''
+  Generates `count` instances with a known optimum of `num_boxes` boxes, which get tighter from
+  the first to the last one. The fill ratio goes up evenly over the given range, so the search
+  has less and less room to spare. Instance `n` is generated with `seed + n`.
+  '''
+  low, high = tightness_range
+  problems = []
+  for n in range(count):
+    fill_ratio = low + (high - low) * n / max(1, count - 1)
+    problems.append(BoxProblem.with_known_optimum(box_length, num_boxes, seed=seed + n, fill_ratio=fill_ratio))
+  return problems
+
+# If called directly, generate a directory of seeded instances
+if __name__ == "__main__":
+  parser = ArgumentParser()
+  parser.add_argument(
+    "--output",
+    type=str,
+    help="Directory to write the instances to",
+    required=True
+  )
+  parser.add_argument(
+    "--count",
+    type=int,
+    help="Number of instances to generate",
+    default=5
+  )
+  add_problem_args(parser, rect_number=50, rect_size="1-10", box_length=15)
+  parser.add_argument(
+    "--known-optimum",
+    type=int,
+    help="Tile this many boxes completely instead of drawing random rects, so the optimum is known"
+  )
+  parser.add_argument(
+    "--splittable",
+    action="store_true",
+    help="Mark all rects as splittable, so they may be cut into smaller pieces"
+  )
+  parser.add_argument(
+    "--split-oversized",
+    type=int,
+    help="Cut splittable rects with a side longer than this into pieces before writing the instances"
+  )
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="Seed of the first instance, the following ones count up from it",
+    default=0
+  )
+
+  args = parser.parse_args()
+  os.makedirs(args.output, exist_ok=True)
+  for n in range(args.count):
+    instance_seed = args.seed + n
+    if args.known_optimum is not None:
+      instance = BoxProblem.with_known_optimum(args.box_length, args.known_optimum, seed=instance_seed)
+    else:
+      instance = BoxProblem(
+        box_length=args.box_length,
+        n_rect=args.rect_number,
+        w_range=parse_range(args.rect_x),
+        h_range=parse_range(args.rect_y),
+        seed=instance_seed
+      )
+    if args.splittable:
+      for box in instance.current_solution.boxes.values():
+        for rect in box.rects.values():
+          rect.splittable = True
+    if args.split_oversized is not None:
+      instance.split_oversized(args.split_oversized)
+    write_problem(instance, os.path.join(args.output, f"instance_{instance_seed}.json"))
@@ -3,113 +3,271 @@ Main application module of this project.
 '''
 
 import logging
-from math import sqrt, floor
 from argparse import ArgumentParser
-import threading
-from threading import Event
 import random
+from copy import deepcopy
+from functools import partial
+from typing import Callable, Optional
 import FreeSimpleGUI as sg
 
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
 from problem.box_problem.box_problem import BoxProblem
 from problem.box_problem.box_solution import BoxSolution
-from algorithms import OptimizationAlgorithm, get_algo_by_name
-from modes import get_available_modes, get_mode_by_name
+from algorithms import OptimizationAlgorithm, LocalSearch, TieBreak, get_algo_by_name
+from modes import Move, Neighborhood, GeometricOverlap, LARGE_NEIGHBORHOOD_SIZE, get_available_modes, get_mode_by_name
 from config import RunConfiguration, show_config_picker
-
-BOX_SPACING = 0.5
+from stop_conditions import StopCondition, ScoreAtMost, Stagnation
+from rendering import paint_solution, solution_extent, Palette, DEFAULT_PALETTE, COLOR_BLIND_PALETTE
+from instances import read_problem_directory, difficulty_ramp
+from plotting import export_convergence_plot
+from session import Session, write_session, read_session
+from worker import SearchWorker, Snapshot
 
 # TODO: Assignment calls for gui to be able to re-generate instances and restart with other algo / mode
 
 logger = logging.getLogger(__name__)
 
-def draw_solution(graph: sg.Graph, solution: BoxSolution, scaling_factor: float, erase: bool = False):
+MAX_PREVIEW_NEIGHBORS = 4
+'''Number of the best neighbors to show when previewing neighbors'''
+THUMBNAIL_SIZE = 200
+'''Canvas size of a neighbor preview in pixels'''
+DEMO_OPTIMAL_BOXES = 4
+'''Number of boxes the instances of the demo are tiled from'''
+DEMO_MAX_TICKS = 100
+'''Number of ticks after which the demo moves on, even if the algorithm did not converge'''
+MIN_DETAIL_PIXELS = 12
+'''On-screen side length in pixels below which a box is only drawn as a fill of its occupancy'''
+SHUTDOWN_TIMEOUT = 2.0
+'''Seconds a running tick gets to finish when the window is closed'''
+
+def draw_solution(
+    graph: sg.Graph,
+    solution: BoxSolution,
+    scaling_factor: float,
+    erase: bool = False,
+    show_rotation: bool = False,
+    show_capacity: bool = False,
+    palette: Palette = DEFAULT_PALETTE
+  ):
   '''
-  Draws the given box problem solution in the graph
+  Draws the given box problem solution in the graph.
+  With `show_rotation`, rects that are flipped from their original orientation get a diagonal line.
+  With `show_capacity`, every box shows how many rects from other boxes would still fit in.
+  Boxes too small to make out their rects are drawn as a single fill and those outside of the graph not at all.
   '''
+  # A change to any box changes the capacity of all others, so they are drawn again on a clean graph
+  # instead of piling up labels, and only computed again once something changed
+  if show_capacity and not erase:
+    if not any(box.needs_redraw for box in solution.boxes.values()):
+      return
+    erase = True
+
   if erase:
     graph.erase()
 
-  # Pre-calculate constants
-  boxes_per_row = floor(sqrt(len(solution.boxes)))
-  scaled_side_length = solution.side_length * scaling_factor
-  scaled_spacing = BOX_SPACING * scaling_factor
-
-  # Draw the boxes
-  for box_idx, box in enumerate(list(solution.boxes.values())):
-
-    # Skip box if it doesn't need to be drawn again
-    #  unless we erased the whole graph before
-    if not (box.needs_redraw or erase):
-      continue
-    box.needs_redraw = False
-
-    row = box_idx % boxes_per_row
-    col = floor(box_idx / boxes_per_row)
-
-    box_left = col * (scaled_side_length + scaled_spacing)
-    box_top = row * (scaled_side_length + scaled_spacing)
-
-    # Draw box
-    graph.draw_rectangle(
-      top_left=(box_left, box_top),
-      bottom_right=(box_left + scaled_side_length, box_top + scaled_side_length),
-      fill_color='gray'
-    )
+  # Translate the pixel threshold into graph coordinates and clip to the visible area of the graph
+  (left, bottom), (right, top) = graph.BottomLeft, graph.TopRight
+  pixels_per_unit = graph.CanvasSize[0] / abs(right - left)
+  clip = (min(left, right), min(top, bottom), max(left, right), max(top, bottom))
 
-    # Also paint the box's rectangles
-    for rect in list(box.rects.values()):
-      rect_left = box_left + rect.get_x() * scaling_factor
-      rect_top = box_top + rect.get_y() * scaling_factor
-      if rect.highlighted:
-        color = 'blue'
-        # Clear highlighted flag, redraw box in next iteration
-        rect.highlighted = False
-        box.needs_redraw = True
-      else:
-        color = 'red'
-      graph.draw_rectangle(
-        top_left=(rect_left, rect_top),
-        bottom_right=(rect_left + rect.get_width() * scaling_factor, rect_top + rect.get_height() * scaling_factor),
-        fill_color=color
-      )
+  for command in paint_solution(
+      solution, scaling_factor, erase, show_rotation, show_capacity, palette,
+      min_detail_size=MIN_DETAIL_PIXELS / pixels_per_unit,
+      clip=clip
+    ):
+    command.draw(graph)
 
-    # Paint the box's free coordinate search space
-    for (x, y) in list(box.get_adjacent_coordinates()):
-      dot_left = box_left + (x - 0.1) * scaling_factor
-      dot_top = box_top + (y - 0.1) * scaling_factor
-      graph.draw_rectangle(
-        top_left=(dot_left, dot_top),
-        bottom_right=(dot_left + 0.2 * scaling_factor, dot_top + 0.2 * scaling_factor),
-        fill_color='blue'
-      )
+def current_palette(values: dict) -> Palette:
+  '''Returns the palette picked in the gui, given the values of the window'''
+  return COLOR_BLIND_PALETTE if values['color_blind'] else DEFAULT_PALETTE
 
-def tick_thread_wrapper(algo: OptimizationAlgorithm, window: sg.Window, tick_complete: Event, redraw_complete: Event):
-  '''Wrapper for executing the tick method in its own thread'''
-  # Disable the tick button
-  window["tick_btn"].update(disabled=True, text="Working...")
+def draw_bound_gauge(gauge: sg.Graph, text: sg.Text, box_count: int, lower_bound: int):
+  '''
+  Draws a bar showing how close the box count is to the lower bound.
+  Green means the bound is reached, yellow that it's a box or two above and red that it's far off.
+  '''
+  gauge.erase()
+  if not box_count:
+    text.update(f"Lower bound: {lower_bound}")
+    return
+
+  gap = box_count - lower_bound
+  if gap <= 0:
+    color = 'green'
+  elif gap <= 2:
+    color = 'yellow'
+  else:
+    color = 'red'
+
+  # Bar fills up the closer one gets to the bound
+  gauge.draw_rectangle(top_left=(0, 0), bottom_right=(100, 10), fill_color='white')
+  gauge.draw_rectangle(top_left=(0, 0), bottom_right=(100 * min(1.0, lower_bound / box_count), 10), fill_color=color)
+  text.update(f"{box_count} boxes / lower bound {lower_bound}")
+
+def show_neighbor_preview(algo: LocalSearch, palette: Palette = DEFAULT_PALETTE) -> Optional[Move]:
+  '''
+  Shows the best neighbors of the current solution as thumbnails and lets the user pick one.
+  Returns the move to the picked neighbor or `None` if the preview was cancelled.
+  '''
+  solution = algo.problem.current_solution
+  candidates = algo.best_neighbors(MAX_PREVIEW_NEIGHBORS)
+  if len(candidates) == 0:
+    sg.popup("No neighbors could be found", title="Preview neighbors")
+    return None
+
+  # Lay out every candidate by applying its move just for painting, like the neighborhoods do for scoring
+  thumbnails = []
+  for candidate in candidates:
+    candidate.move.apply_to_solution(solution)
+    extent = max(1, *solution_extent(solution, 1))
+    thumbnails.append((extent, paint_solution(solution, 1, erase=True, palette=palette)))
+    candidate.move.undo(solution)
+
+  columns = [
+    sg.Column([
+      [sg.Graph(
+        background_color='white',
+        canvas_size=(THUMBNAIL_SIZE, THUMBNAIL_SIZE),
+        graph_bottom_left=(0, extent),
+        graph_top_right=(extent, 0),
+        enable_events=True,
+        key=("candidate", i)
+      )],
+      [sg.Text(f"{i + 1}. {candidate.score}", size=(28, 3))],
+      [sg.Button("Commit", k=("commit", i))]
+    ])
+    for i, (candidate, (extent, _)) in enumerate(zip(candidates, thumbnails))
+  ]
+  window = sg.Window("Preview neighbors", [columns, [sg.Button("Cancel")]], modal=True, finalize=True)
+  for i, (_, commands) in enumerate(thumbnails):
+    for command in commands:
+      command.draw(window[("candidate", i)])
 
-  # Get number of ticks to run
-  num_ticks = int(window["num_ticks"].get())
-  for _ in range(num_ticks):
-    algo.tick()
-    # Set tick as complete and wait until redraw is finished
-    tick_complete.set()
-    redraw_complete.wait()
-    redraw_complete.clear()
+  # Clicking a thumbnail or its button commits that candidate
+  chosen = None
+  while True:
+    event, _ = window.read()
+    if event in (sg.WIN_CLOSED, "Cancel"):
+      break
+    if isinstance(event, tuple):
+      chosen = candidates[event[1]].move
+      break
+  window.close()
+  return chosen
+
+def tick_job(num_ticks: int, stop_condition: StopCondition = None) -> Callable[[SearchWorker], str]:
+  '''Job for the search worker which runs the given number of ticks, or fewer if the target is reached'''
+  def run(worker: SearchWorker) -> str:
+    algo = worker.algorithm
+    for i in range(num_ticks):
+      if worker.should_stop():
+        return f"Stopped after {i} ticks"
+      algo.tick()
+      algo.check_lower_bound()
+      algo.record_progress()
+      worker.after_tick()
+
+      # Stop early if the target was reached
+      if stop_condition is not None and stop_condition.is_met(algo):
+        return f"Target reached: {stop_condition} after {i + 1} ticks"
+    if stop_condition is not None:
+      return f"Target not reached: {stop_condition} within {num_ticks} ticks"
+    return ""
+  return run
+
+def generate_problem(config: RunConfiguration, seed: int) -> BoxProblem:
+  '''Generates the problem instance for the given seed'''
+  return BoxProblem(
+    box_length=config.box_length,
+    n_rect=config.rect_number,
+    w_range=config.rect_x_size,
+    h_range=config.rect_y_size,
+    seed=seed
+  )
 
-  # Enable button again
-  window["tick_btn"].update(disabled=False, text="Tick")
+def init_algorithm(config: RunConfiguration, mode, problem: BoxProblem, window: sg.Window) -> OptimizationAlgorithm:
+  '''Initializes the configured algorithm on the given problem instance'''
+  optimization_algorithm: OptimizationAlgorithm = config.algorithm(problem, mode)
+  if isinstance(optimization_algorithm, LocalSearch):
+    optimization_algorithm.set_tie_break(config.tie_break)
+  # The starting solution is the baseline of the progress counters
+  optimization_algorithm.record_progress()
+  attach_acceptance_log(config, optimization_algorithm, window)
+  return optimization_algorithm
+
+def attach_acceptance_log(config: RunConfiguration, optimization_algorithm: OptimizationAlgorithm, window: sg.Window):
+  '''Shows the acceptance decisions of the algorithm in the log panel, if it is configured'''
+  if config.log_acceptance:
+    # Hand decisions over to the gui thread as events, since they happen in the tick thread
+    optimization_algorithm.add_acceptance_observer(lambda d: window.write_event_value("acceptance", str(d)))
+
+def demo_job(
+    config: RunConfiguration,
+    mode,
+    problems: list[BoxProblem],
+    window: sg.Window
+  ) -> Callable[[SearchWorker], str]:
+  '''
+  Job for the search worker which runs the algorithm on one instance after another, each until it converged.
+  The gui thread is handed every new algorithm and the result of every instance as events.
+  '''
+  def run(worker: SearchWorker) -> str:
+    for n, problem in enumerate(problems):
+      algo = init_algorithm(config, mode, problem, window)
+      worker.algorithm = algo
+      worker.box_count_history = [problem.current_solution.get_heuristic_score().box_count]
+      window.write_event_value("demo_instance", (n, algo))
+      worker.publish(force=True)
+
+      stagnation = Stagnation()
+      for _ in range(DEMO_MAX_TICKS):
+        if worker.should_stop():
+          return f"Demo stopped at instance {n + 1}/{len(problems)}"
+        algo.tick()
+        algo.check_lower_bound()
+        algo.record_progress()
+        worker.after_tick()
+        if stagnation.is_met(algo):
+          break
+
+      window.write_event_value("demo_result", (n, algo))
+    return f"Demo finished after {len(problems)} instances"
+  return run
+
+def demo_result_row(n: int, algo: OptimizationAlgorithm) -> list:
+  '''Row of the demo results table, the gap is taken to the lower bound if the optimum is not known'''
+  problem = algo.problem
+  box_count = problem.current_solution.get_heuristic_score().box_count
+  optimum = problem.known_optimum if problem.known_optimum is not None else problem.get_lower_bound()
+  return [
+    n + 1,
+    f"{problem.tightness():.2f}",
+    box_count,
+    str(problem.known_optimum) if problem.known_optimum is not None else f">= {optimum}",
+    box_count - optimum if box_count is not None else "-"
+  ]
 
 # Main application part
 def show_app(config: RunConfiguration):
   '''Shows the main application for algorithm visualization'''  
+  # Problems to cycle through, if a directory was given
+  problems = read_problem_directory(config.problem_dir) if config.problem_dir else []
+  problem_index = 0 if len(problems) > 0 else None
+  # The overlap penalty only has an effect on the overlap neighborhood
+  has_overlap = GeometricOverlap in get_available_modes(config.algorithm)
+
   # GUI initialization stuff
   layout = [
     [
       sg.Button("Tick", k="tick_btn"),
+      sg.Button("Stop", k="stop_btn", disabled=True),
       sg.Text("Number of ticks"),
       sg.Input("10", k="num_ticks"),
-      sg.Slider(range=(1, 10), default_value=2, resolution=0.5, key='scaling', enable_events=True, orientation='h'),
+      sg.Slider(range=(0.1, 10), default_value=2, resolution=0.1, key='scaling', enable_events=True, orientation='h'),
+      sg.Checkbox("Show rotations", k="show_rotation", enable_events=True),
+      sg.Checkbox("Show capacity", k="show_capacity", enable_events=True),
+      sg.Checkbox("Color-blind palette", k="color_blind", enable_events=True),
+      sg.Checkbox("Preview neighbors", k="preview_neighbors", visible=issubclass(config.algorithm, LocalSearch)),
       sg.Listbox(
         [e.__name__ for e in get_available_modes(config.algorithm)],
         select_mode='LISTBOX_SELECT_MODE_EXTENDED',
@@ -117,6 +275,60 @@ def show_app(config: RunConfiguration):
         key='mode',
         default_values=[config.mode.__name__],
         size=(25, 3)
+      ),
+      sg.Text("Seed"),
+      sg.Input("", k="seed", size=10),
+      sg.Button("\u2684", k="randomize_seed", tooltip="Randomize seed"),
+      sg.Button("Reset", k="reset_btn"),
+      sg.Text("Box size"),
+      sg.Input("", k="box_size", size=4),
+      sg.Button("Apply box size", k="apply_box_size"),
+      sg.Button("Previous", k="prev_problem", visible=len(problems) > 0),
+      sg.Button("Next", k="next_problem", visible=len(problems) > 0),
+      sg.Text("", k="problem_name"),
+      sg.Text("", k="status")
+    ],
+    [
+      sg.Graph(
+        background_color='white',
+        canvas_size=(100, 10),
+        graph_bottom_left=(0, 10),
+        graph_top_right=(100, 0),
+        key='bound_gauge'
+      ),
+      sg.Text("", k="bound_text"),
+      sg.Text("", k="fill_text"),
+      sg.Text("", k="progress_text"),
+      sg.Text("", k="candidates_text"),
+      sg.Checkbox("Show baseline", k="show_baseline", enable_events=True),
+      sg.Text("", k="baseline_text"),
+      sg.Text("Overlap penalty", visible=has_overlap),
+      sg.Slider(
+        range=(0, 10),
+        default_value=GeometricOverlap.overlap_penalty,
+        resolution=0.1,
+        key='overlap_penalty',
+        enable_events=True,
+        orientation='h',
+        visible=has_overlap
+      ),
+      sg.Text("", k="overlap_text", visible=has_overlap),
+      sg.Button("Export plot", k="export_plot"),
+      sg.Button("Save session", k="save_session"),
+      sg.Button("Load session", k="load_session")
+    ],
+    [
+      sg.Button("Start demo", k="demo_btn"),
+      sg.Text("Instances"),
+      sg.Input("5", k="demo_count", size=4),
+      sg.Text("Tightness"),
+      sg.Input("0.8-1.0", k="demo_tightness", size=9),
+      sg.Table(
+        values=[],
+        headings=["Instance", "Tightness", "Boxes", "Optimum", "Gap"],
+        k="demo_results",
+        num_rows=4,
+        expand_x=True
       )
     ],
     [
@@ -131,27 +343,101 @@ def show_app(config: RunConfiguration):
       )
     ]
   ]
+  # Optional log panel for the acceptance decisions of the algorithm
+  if config.log_acceptance:
+    layout.append([sg.Multiline(size=(80, 8), k="acceptance_log", autoscroll=True, disabled=True, expand_x=True)])
   window = sg.Window("Optimierungsalgorithmen Programmierprojekt", layout, resizable=True)
   graph = window['graph']
   window.finalize()
 
+  def current_problem() -> BoxProblem:
+    '''Returns a fresh copy of the selected problem from the directory'''
+    name, problem = problems[problem_index]
+    window["problem_name"].update(f"{name} ({problem_index + 1}/{len(problems)})")
+    return deepcopy(problem)
+
   # OptAlgo stuff
-  optimization_problem: BoxProblem = BoxProblem(
-    box_length=config.box_length,
-    n_rect=config.rect_number,
-    w_range=config.rect_x_size,
-    h_range=config.rect_y_size
-  )
-  optimization_algorithm: OptimizationAlgorithm = config.algorithm(optimization_problem, config.mode)
+  if problem_index is not None:
+    optimization_problem = current_problem()
+  else:
+    if config.problem_dir:
+      window["status"].update(f"No problems found in {config.problem_dir}")
+    optimization_problem = generate_problem(config, config.seed)
+  # Without an instance to start on there is nothing to show
+  try:
+    optimization_problem.check_placeable()
+    optimization_algorithm = init_algorithm(config, config.mode, optimization_problem, window)
+  except ValueError as e:
+    sg.popup_error(f"Could not start on this instance: {e}", title="Infeasible instance")
+    window.close()
+    return
+  stop_condition = ScoreAtMost(config.target_box_count) if config.target_box_count is not None else None
+
+  # Show the seed that was actually used, even if it was picked at random
+  window["seed"].update(str(optimization_algorithm.problem.seed) if optimization_algorithm.problem.seed is not None else "")
+
+  def update_stats(snapshot: Optional[Snapshot] = None):
+    '''
+    Updates the lower bound gauge, the fill statistics, the progress and the overlap area,
+    of the snapshot if given and otherwise of the current solution
+    '''
+    solution = snapshot.solution if snapshot is not None else optimization_algorithm.problem.current_solution
+    progress = snapshot.progress if snapshot is not None else optimization_algorithm.progress
+    candidate_stats = snapshot.candidate_stats if snapshot is not None else optimization_algorithm.candidate_stats
+    draw_bound_gauge(
+      window["bound_gauge"],
+      window["bound_text"],
+      solution.get_heuristic_score().box_count,
+      optimization_algorithm.problem.get_lower_bound()
+    )
+    window["fill_text"].update(f"Fill: {solution.fill_stats()}")
+    window["progress_text"].update(f"Progress: {progress}")
+    # Selection schemas and algorithms with their own moves don't score any candidates
+    if candidate_stats.generated > 0:
+      window["candidates_text"].update(f"Candidates: {candidate_stats}")
+    else:
+      window["candidates_text"].update("")
+    # Frame the current box count by the one of the trivial solution the instance started with
+    if window["show_baseline"].get():
+      baseline = optimization_algorithm.problem.get_trivial_box_count()
+      window["baseline_text"].update(f"{baseline} \u2192 {solution.get_heuristic_score().box_count} boxes")
+    else:
+      window["baseline_text"].update("")
+    window["overlap_text"].update(f"Overlap area: {solution.compute_overlap_area()}")
+  update_stats()
+
+  def redraw(values: dict, solution: Optional[BoxSolution] = None, erase: bool = True):
+    '''
+    Draws the solution, the current one if none is given, with the scaling, overlays and palette picked in the window.
+    Erases the graph first by default, otherwise only boxes that changed are drawn again.
+    '''
+    draw_solution(
+      graph,
+      solution if solution is not None else optimization_algorithm.problem.current_solution,
+      values['scaling'],
+      erase=erase,
+      show_rotation=values['show_rotation'],
+      show_capacity=values['show_capacity'],
+      palette=current_palette(values)
+    )
 
   draw_solution(graph, optimization_algorithm.problem.current_solution, scaling_factor=2, erase=True)
-
-  # Events to signal the main thread to redraw the current solution
-  tick_complete_event = Event()
-  redraw_complete_event = Event()
+  window["box_size"].update(str(optimization_algorithm.problem.current_solution.side_length))
 
   # Keep track of last drawn to erase only when boxcount changes
   last_box_count = optimization_algorithm.problem.current_solution.get_heuristic_score().box_count
+  # Runs ticks and demos in the background and keeps the box count after every tick for the convergence plot
+  worker = SearchWorker(lambda message: window.write_event_value("worker_done", message))
+  worker.box_count_history = [last_box_count]
+  # Snapshot that was drawn last while the worker is running, and the ids of its boxes still due for a redraw
+  shown_snapshot: Optional[Snapshot] = None
+  pending_redraw: set[int] = set()
+  # Set while a demo runs, so its events are ignored once it was stopped for another instance
+  demo_active = False
+
+  # Rows of the results table of a running or finished demo
+  demo_results = []
+  demo_count = 0
 
   while True:
     event, values = window.read(timeout=5)
@@ -160,35 +446,244 @@ def show_app(config: RunConfiguration):
       case "Exit" | sg.WIN_CLOSED:
         break
       case "tick_btn":
-        threading.Thread(
-          target=tick_thread_wrapper,
-          args=(optimization_algorithm, window, tick_complete_event, redraw_complete_event),
-          daemon=True
-        ).start()
+        # Warn before a tick that might take minutes
+        strategy = optimization_algorithm.strategy
+        if issubclass(strategy, Neighborhood):
+          size = strategy.estimate_size(optimization_algorithm.problem.current_solution)
+          if size > LARGE_NEIGHBORHOOD_SIZE:
+            answer = sg.popup_yes_no(f"This will generate ~{size:,} candidates per tick, continue?", title="Large neighborhood")
+            if answer != "Yes":
+              continue
+        # Let the user pick the neighbor instead of committing the best one
+        if values["preview_neighbors"] and isinstance(optimization_algorithm, LocalSearch):
+          move = show_neighbor_preview(
+            optimization_algorithm,
+            current_palette(values)
+          )
+          if move is not None:
+            optimization_algorithm.commit_move(move)
+            optimization_algorithm.check_lower_bound()
+            optimization_algorithm.record_progress()
+          redraw(values)
+          if move is not None:
+            last_box_count = optimization_algorithm.problem.current_solution.get_heuristic_score().box_count
+            worker.box_count_history.append(last_box_count)
+            update_stats()
+          continue
+        window["tick_btn"].update(disabled=True, text="Working...")
+        window["stop_btn"].update(disabled=False)
+        worker.start(optimization_algorithm, tick_job(int(window["num_ticks"].get()), stop_condition))
+      case "stop_btn":
+        worker.request_stop()
+        window["stop_btn"].update(disabled=True)
+      case "worker_done":
+        if values["worker_done"]:
+          window["status"].update(values["worker_done"])
+        window["tick_btn"].update(disabled=False, text="Tick")
+        window["demo_btn"].update(disabled=False)
+        window["stop_btn"].update(disabled=True)
+        demo_active = False
+      case "randomize_seed" | "reset_btn" | "prev_problem" | "next_problem":
+        # Don't pull the solution away from under a running tick
+        demo_active = False
+        if not worker.stop():
+          continue
+        if event == "randomize_seed":
+          window["seed"].update(str(random.randrange(2**32)))
+          # A generated instance replaces the one from the directory until next / previous is used
+          problem_index = None
+          window["problem_name"].update("")
+        if event in ("prev_problem", "next_problem"):
+          # Coming back from a generated instance starts at the first problem again
+          if problem_index is None:
+            problem_index = 0
+          else:
+            step = 1 if event == "next_problem" else -1
+            problem_index = (problem_index + step) % len(problems)
+
+        if problem_index is not None:
+          # Reload the instance from the directory
+          optimization_problem = current_problem()
+        else:
+          try:
+            seed = int(window["seed"].get())
+          except ValueError:
+            window["status"].update("Seed must be a number")
+            continue
+          # Re-generate the instance
+          optimization_problem = generate_problem(config, seed)
+        # Restart with the currently selected mode, instances that can't be packed keep the current one
+        mode = get_mode_by_name(config.algorithm, values['mode'][0]) or config.mode
+        try:
+          optimization_problem.check_placeable()
+          optimization_algorithm = init_algorithm(config, mode, optimization_problem, window)
+        except ValueError as e:
+          window["status"].update(f"Could not start on this instance: {e}")
+          continue
+        window["status"].update("")
+        window["box_size"].update(str(optimization_problem.current_solution.side_length))
+        redraw(values)
+        last_box_count = optimization_algorithm.problem.current_solution.get_heuristic_score().box_count
+        worker.box_count_history = [last_box_count]
+        update_stats()
+      case "apply_box_size":
+        # Don't resize the boxes under a running tick
+        demo_active = False
+        if not worker.stop():
+          continue
+        try:
+          side_length = int(values["box_size"])
+          if side_length < 1:
+            raise ValueError("Box size must be at least 1")
+          opened = optimization_algorithm.problem.resize_boxes(side_length)
+        except ValueError as e:
+          window["status"].update(f"Could not resize boxes: {e}")
+          continue
+        # The state of the algorithm refers to the old box size, so start it over on the repaired solution
+        mode = get_mode_by_name(config.algorithm, values['mode'][0]) or config.mode
+        optimization_algorithm = init_algorithm(config, mode, optimization_algorithm.problem, window)
+        redraw(values)
+        last_box_count = optimization_algorithm.problem.current_solution.get_heuristic_score().box_count
+        worker.box_count_history = [last_box_count]
+        update_stats()
+        window["status"].update(f"Resized boxes to {side_length}, the repair needed {opened} new boxes")
+      case "export_plot":
+        path = sg.popup_get_file(
+          "Save convergence plot as",
+          save_as=True,
+          default_extension=".png",
+          file_types=(("PNG", "*.png"),)
+        )
+        if path:
+          export_convergence_plot(worker.box_count_history, optimization_algorithm.problem.get_lower_bound(), path)
+          window["status"].update(f"Saved plot to {path}")
+      case "save_session":
+        # Don't save a solution that is being modified by a running tick
+        if worker.is_running():
+          continue
+        path = sg.popup_get_file(
+          "Save session as",
+          save_as=True,
+          default_extension=".json",
+          file_types=(("JSON", "*.json"),)
+        )
+        if path:
+          write_session(Session.capture(optimization_algorithm, worker.box_count_history), path)
+          window["status"].update(f"Saved session to {path}")
+      case "load_session":
+        demo_active = False
+        if not worker.stop():
+          continue
+        path = sg.popup_get_file("Load session", file_types=(("JSON", "*.json"),))
+        if not path:
+          continue
+        try:
+          session = read_session(path)
+          # The mode list and config only fit sessions of the same algorithm
+          if session.algorithm != config.algorithm.__name__:
+            raise ValueError(f"Session uses {session.algorithm}, but this run uses {config.algorithm.__name__}")
+          optimization_algorithm = session.restore()
+        #pylint: disable=W0718
+        except Exception as e:
+          window["status"].update(f"Could not load session: {e}")
+          continue
+        attach_acceptance_log(config, optimization_algorithm, window)
+        optimization_algorithm.record_progress()
+        # A loaded instance replaces the one from the directory until next / previous is used
+        problem_index = None
+        window["problem_name"].update("")
+        window["seed"].update(str(optimization_algorithm.problem.seed) if optimization_algorithm.problem.seed is not None else "")
+        window["box_size"].update(str(optimization_algorithm.problem.current_solution.side_length))
+        window["mode"].update(set_to_index=[
+          m.__name__ for m in get_available_modes(config.algorithm)
+        ].index(session.mode))
+        worker.box_count_history = session.box_count_history
+        last_box_count = optimization_algorithm.problem.current_solution.get_heuristic_score().box_count
+        redraw(values)
+        update_stats()
+        window["status"].update(f"Resumed session from {path}")
+      case "demo_btn":
+        if worker.is_running():
+          continue
+        try:
+          demo_count = int(values["demo_count"])
+          low, high = [float(t) for t in values["demo_tightness"].split("-")]
+        except ValueError:
+          window["status"].update("Demo needs a number of instances and a tightness range like 0.8-1.0")
+          continue
+        demo_problems = difficulty_ramp(
+          config.box_length,
+          DEMO_OPTIMAL_BOXES,
+          demo_count,
+          (low, high),
+          seed=random.randrange(2**32)
+        )
+        demo_results = []
+        window["demo_results"].update(values=demo_results)
+        window["status"].update("")
+        mode = get_mode_by_name(config.algorithm, values['mode'][0]) or config.mode
+        window["tick_btn"].update(disabled=True, text="Demo running...")
+        window["demo_btn"].update(disabled=True)
+        window["stop_btn"].update(disabled=False)
+        demo_active = True
+        worker.start(optimization_algorithm, demo_job(config, mode, demo_problems, window))
+      case "demo_instance":
+        # The demo may have been stopped for another instance before the event came through
+        if not demo_active:
+          continue
+        demo_n, optimization_algorithm = values["demo_instance"]
+        # The demo instances replace the one from the directory until next / previous is used
+        problem_index = None
+        window["problem_name"].update(f"Demo {demo_n + 1}/{demo_count}")
+        window["seed"].update(str(optimization_algorithm.problem.seed))
+        # The instance itself is drawn from the snapshot the worker left for it
+        window["box_size"].update(str(optimization_algorithm.problem.current_solution.side_length))
+      case "demo_result":
+        if demo_active:
+          demo_results.append(demo_result_row(*values["demo_result"]))
+          window["demo_results"].update(values=demo_results)
+      case "show_baseline":
+        update_stats(shown_snapshot if worker.is_running() else None)
+      case "overlap_penalty":
+        # Picked up by the next scoring, so it takes effect on the next tick
+        GeometricOverlap.overlap_penalty = values['overlap_penalty']
+      case "acceptance":
+        window["acceptance_log"].print(values["acceptance"])
       case "mode":
         mode = get_mode_by_name(optimization_algorithm.__class__, values['mode'][0])
         if mode is not None:
-          optimization_algorithm.set_strategy(mode)
-      case "scaling":
-        # Wait until a possible tick is complete and redraw the whole solution
-        # tick_complete_event.wait()
-        draw_solution(graph, optimization_algorithm.problem.current_solution, values['scaling'], erase=True)
-        redraw_complete_event.set()
-
-    # If the redraw event was set, draw & refresh gui
-    if tick_complete_event.is_set():
-      current_solution = optimization_algorithm.problem.current_solution
+          # A running tick keeps the neighborhood it started with, the next one uses the new one
+          worker.between_ticks(partial(optimization_algorithm.set_strategy, mode))
+      case "scaling" | "show_rotation" | "show_capacity" | "color_blind":
+        # Redraw the whole solution, the snapshot that is shown if the worker is still changing the real one
+        shown = shown_snapshot.solution if worker.is_running() and shown_snapshot is not None \
+          else optimization_algorithm.problem.current_solution
+        redraw(values, shown)
+
+    # Draw the latest snapshot of the worker, ticks that happened since the last one are skipped
+    snapshot = worker.latest()
+    if snapshot is not None:
+      current_solution = snapshot.solution
+      # Boxes of the last snapshot that were not drawn fully yet, e.g. to clear highlights, get their turn now
+      for box_id in pending_redraw:
+        if box_id in current_solution.boxes:
+          current_solution.boxes[box_id].needs_redraw = True
       # Check if we need to redraw the whole solution because box count changed
-      if current_solution.get_heuristic_score().box_count != last_box_count:
+      if snapshot.redraw_all or current_solution.get_heuristic_score().box_count != last_box_count:
         erase = True
         last_box_count = current_solution.get_heuristic_score().box_count
       else:
         erase = False
       # Actually draw
-      draw_solution(graph, current_solution, values['scaling'], erase)
+      redraw(values, current_solution, erase=erase)
+      pending_redraw = {box_id for (box_id, box) in current_solution.boxes.items() if box.needs_redraw}
+      shown_snapshot = snapshot
+      update_stats(snapshot)
       window.refresh()
-      tick_complete_event.clear()
-      redraw_complete_event.set()
+
+  # Let a running tick finish, its thread is a daemon and doesn't keep the program alive if it doesn't in time
+  worker.shutdown(SHUTDOWN_TIMEOUT)
+  window.close()
 
 # Entrypoint, will either get config as args or via gui dialogue
 if __name__ == "__main__":
@@ -204,25 +699,16 @@ if __name__ == "__main__":
     type=str,
     help=f"Possible values: {[m.__name__ for m in get_available_modes(None)]}"
   )
+  add_problem_args(parser)
   parser.add_argument(
-    "--rect-number",
+    "--target-boxes",
     type=int,
-    help="Just a number"
+    help="Stop as soon as a solution with at most this many boxes is found"
   )
   parser.add_argument(
-    "--rect-x",
-    type=str,
-    help="Min-max range (e.g. 5-12)"
-  )
-  parser.add_argument(
-    "--rect-y",
-    type=str,
-    help="Min-max range (e.g. 5-12)"
-  )
-  parser.add_argument(
-    "--box-length",
-    type=int,
-    help="Just a number"
+    "--log-acceptance",
+    action="store_true",
+    help="Show a log of every acceptance decision of the algorithm"
   )
   parser.add_argument(
     "--seed",
@@ -230,11 +716,17 @@ if __name__ == "__main__":
     help="RNG seed"
   )
   parser.add_argument(
-    "--log",
+    "--tie-break",
+    type=str,
+    help=f"How the local search decides between neighbors with the same score: {[t.name for t in TieBreak]}",
+    default=TieBreak.RANDOM.name
+  )
+  parser.add_argument(
+    "--problem-dir",
     type=str,
-    help="Log level can be one of ['DEBUG', 'INFO', 'WARNING', 'ERROR', 'CRITICAL']",
-    default="WARNING"
+    help="Directory of JSON problem instances to cycle through instead of generating one"
   )
+  add_log_args(parser)
 
   args = parser.parse_args()
 
@@ -242,11 +734,7 @@ if __name__ == "__main__":
   if args.seed:
     random.seed(args.seed)
 
-  # Set log level
-  numeric_level = getattr(logging, args.log.upper(), None)
-  if not isinstance(numeric_level, int):
-    raise ValueError(f"Invalid log level: {args.log}")
-  logging.basicConfig(level=numeric_level)
+  configure_logging(args)
 
   # Try and construct run config from args,
   # and if it fails, show dialogue
@@ -259,9 +747,14 @@ if __name__ == "__main__":
       algorithm=algo,
       mode=mode,
       rect_number=args.rect_number,
-      rect_x_size=range(*[int(i) for i in args.rect_x.split("-")]),
-      rect_y_size=range(*[int(i) for i in args.rect_y.split("-")]),
-      box_length=args.box_length
+      rect_x_size=parse_range(args.rect_x),
+      rect_y_size=parse_range(args.rect_y),
+      box_length=args.box_length,
+      target_box_count=args.target_boxes,
+      log_acceptance=args.log_acceptance,
+      seed=args.seed,
+      problem_dir=args.problem_dir,
+      tie_break=TieBreak[args.tie_break.upper()]
     )
   #pylint: disable=W0718
   except Exception as e:
@@ -270,9 +763,14 @@ if __name__ == "__main__":
       algo_default=args.algorithm,
       mode_default=args.mode,
       rect_number_default=args.rect_number,
-      rect_x_default=range(*[int(i) for i in args.rect_x.split("-")]) if args.rect_x else None,
-      rect_y_default=range(*[int(i) for i in args.rect_y.split("-")]) if args.rect_y else None,
-      box_len_default=args.box_length
+      rect_x_default=parse_range(args.rect_x) if args.rect_x else None,
+      rect_y_default=parse_range(args.rect_y) if args.rect_y else None,
+      box_len_default=args.box_length,
+      target_boxes_default=args.target_boxes,
+      log_acceptance_default=args.log_acceptance,
+      seed_default=args.seed,
+      problem_dir_default=args.problem_dir,
+      tie_break_default=TieBreak[args.tie_break.upper()]
     )
 
   # Launch main app with the config
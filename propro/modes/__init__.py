@@ -6,8 +6,10 @@ as well as their concrete implementations as
 '''
 
 from .mode import Mode
-from .move import Move, ScoredMove
+from .move import Move, CompoundMove, ScoredMove
 from .selection_schemas import SelectionSchema, BySpaceSelection, ByAreaSelection, SelectionMove
-from .neighborhoods import Neighborhood
-from .neighborhoods import Permutation, Geometric, GeometricOverlap
+from .neighborhoods import Neighborhood, NeighborhoodStats, LARGE_NEIGHBORHOOD_SIZE
+from .neighborhoods import EncodedSolution
+from .neighborhoods import Permutation, Geometric, GeometricOverlap, Knapsack, DissolveBox, CoarseToFine, RotateBox, Compact
+from .neighborhood_cache import NeighborhoodCache
 from .util import get_available_modes, get_mode_by_name
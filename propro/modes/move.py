@@ -5,7 +5,7 @@ which brings you from one solution to a neighboring one
 
 from __future__ import annotations
 from dataclasses import dataclass
-from typing import TypeVar, Generic
+from typing import Any, TypeVar, Generic
 from abc import ABC, abstractmethod
 
 from problem.solution import Solution
@@ -24,6 +24,53 @@ class Move(ABC):
   def undo(self, solution: Solution):
     '''Reverts the changes to the solution from this move'''
 
+class CompoundMove(Move):
+  '''
+  Applies several moves as one transaction.
+  The moves are applied in order and if any of them fails, all previous ones are undone again,
+  so the solution is either changed by all of them or by none.
+  The intermediate states are never scored, only the solution after the last move.
+  '''
+  moves: list[Move]
+  '''Moves in the order they are applied'''
+  __applied_count: int
+  '''Number of moves that were applied by the last call'''
+  __order: Any
+  '''Order of the solution before the last call, see `Solution.get_order`'''
+
+  def __init__(self, moves: list[Move]):
+    self.moves = moves
+    self.__applied_count = 0
+    self.__order = None
+
+  def __repr__(self):
+    return f"CompoundMove({self.moves})"
+
+  def apply_to_solution(self, solution: Solution) -> bool:
+    '''
+    Tries to apply all moves to a given solution.
+    Will return false and leave the solution unchanged if one of them fails.
+    '''
+    self.__applied_count = 0
+    self.__order = solution.get_order()
+    for move in self.moves:
+      if not move.apply_to_solution(solution):
+        self.undo(solution)
+        return False
+      self.__applied_count += 1
+    return True
+
+  def undo(self, solution: Solution):
+    '''Undoes all applied moves in reverse order'''
+    if self.__order is None:
+      raise ValueError("Undo called without the move being performed before!")
+    for move in reversed(self.moves[:self.__applied_count]):
+      move.undo(solution)
+    self.__applied_count = 0
+    # A failed move may have reordered the solution as well, so this is needed after undoing nothing too
+    solution.restore_order(self.__order)
+    self.__order = None
+
 H = TypeVar('H', bound=AbstractHeuristic)
 @dataclass
 class ScoredMove(Generic[H]):
@@ -0,0 +1,68 @@
+'''
+Cache for the generated neighbors of solutions that are visited again
+'''
+
+from collections import OrderedDict
+
+from problem.box_problem.box_solution import BoxSolution
+from .neighborhoods import Neighborhood, NeighborhoodStats
+from .move import ScoredMove
+
+class NeighborhoodCache:
+  '''
+  Least recently used cache of the scored neighbors of a solution.
+  Entries are keyed by the neighborhood, its settings and the solution's hash, so
+  changing the neighborhood or one of its settings never returns stale neighbors.
+  '''
+  capacity: int
+  '''Maximum number of neighborhoods to keep'''
+  hits: int
+  '''Number of lookups that could be answered from the cache'''
+  misses: int
+  '''Number of lookups that had to generate the neighbors'''
+
+  __entries: OrderedDict
+
+  def __init__(self, capacity: int = 128):
+    self.capacity = capacity
+    self.hits = 0
+    self.misses = 0
+    self.__entries = OrderedDict()
+
+  def __repr__(self):
+    return f"NeighborhoodCache({len(self.__entries)}/{self.capacity}, hit rate {self.hit_rate():.1%})"
+
+  def get_neighbors_with_stats(
+      self,
+      neighborhood: type[Neighborhood],
+      solution: BoxSolution
+    ) -> tuple[list[ScoredMove], NeighborhoodStats]:
+    '''
+    Returns the neighbors of the solution, generating them only if they are not cached yet,
+    along with the counts of the candidates that had to be enumerated for them. These are empty for a hit.
+    '''
+    if not neighborhood.cacheable:
+      return neighborhood.get_neighbors_with_stats(solution)
+
+    # The recently moved rects are skipped by some neighborhoods, so they are part of the key
+    key = (neighborhood, neighborhood.get_parameters(), solution.solution_hash(), tuple(solution.last_moved_rect_ids))
+    if key in self.__entries:
+      self.hits += 1
+      self.__entries.move_to_end(key)
+      return (self.__entries[key], NeighborhoodStats())
+
+    self.misses += 1
+    (neighbors, stats) = neighborhood.get_neighbors_with_stats(solution)
+    self.__entries[key] = neighbors
+    if len(self.__entries) > self.capacity:
+      self.__entries.popitem(last=False)
+    return (neighbors, stats)
+
+  def hit_rate(self) -> float:
+    '''Returns the fraction of lookups that were answered from the cache'''
+    lookups = self.hits + self.misses
+    return self.hits / lookups if lookups > 0 else 0.0
+
+  def clear(self):
+    '''Drops all cached neighborhoods'''
+    self.__entries.clear()
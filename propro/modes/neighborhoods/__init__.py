@@ -1,6 +1,11 @@
 ''' Module for all neighborhood related stuff '''
 
-from .neighborhood import Neighborhood
-from .permutation import Permutation
+from .neighborhood import Neighborhood, NeighborhoodStats, LARGE_NEIGHBORHOOD_SIZE
+from .permutation import Permutation, EncodedSolution
 from .geometric import Geometric
 from .geometric_overlap import GeometricOverlap
+from .knapsack import Knapsack
+from .dissolve_box import DissolveBox
+from .coarse_to_fine import CoarseToFine
+from .rotate_box import RotateBox
+from .compact import Compact
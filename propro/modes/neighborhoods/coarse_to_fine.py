@@ -0,0 +1,178 @@
+from __future__ import annotations
+import logging
+
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.geometry import Box
+
+from .neighborhood import Neighborhood, NeighborhoodStats
+from .geometric import GeometricMove
+from ..move import ScoredMove
+
+logger = logging.getLogger(__name__)
+
+class CoarseToFine(Neighborhood):
+  '''
+  Geometry-based neighborhood which only tries coordinates on a grid with a certain step.
+  It starts out with a coarse grid, which is fast but approximate, and refines the grid
+  whenever no improving neighbor is found at the current step, until step 1 has converged.
+  '''
+
+  initial_step = 8
+  '''Grid step the search starts with, at least 1. Set it with `set_initial_step`.'''
+
+  refinement_factor = 2
+  '''
+  Factor the step is divided by whenever the search converged at the current step.
+  At least 2, since the step would never get finer otherwise. Set it with `set_refinement_factor`.
+  '''
+
+  step = initial_step
+  '''Grid step that is currently used'''
+
+  converged = False
+  '''Set once no improving neighbor could be found at step 1'''
+
+  cacheable = False
+  '''Refines its step while generating neighbors'''
+
+  @classmethod
+  def set_initial_step(cls, step: int):
+    '''Sets the grid step the search starts with, raises a ValueError if it is below 1'''
+    if step < 1:
+      raise ValueError(f"The initial step must be at least 1, got {step}")
+    cls.initial_step = step
+
+  @classmethod
+  def set_refinement_factor(cls, factor: int):
+    '''Sets the factor the grid step is divided by, raises a ValueError if it is below 2'''
+    if factor < 2:
+      raise ValueError(f"The refinement factor must be at least 2, got {factor}")
+    cls.refinement_factor = factor
+
+  @classmethod
+  def initialize(cls, solution: BoxSolution) -> BoxSolution:
+    '''Starts over with the coarsest grid'''
+    cls.step = cls.initial_step
+    cls.converged = False
+    return solution
+
+  @staticmethod
+  def grid_origins(box: Box, dimension: int, step: int) -> list[int]:
+    '''
+    Origins along one axis of the box at multiples of `step`, plus the one flush with the far edge.
+    If the box has a grid unit, the far one is snapped onto it, while the multiples of `step` may still be off of it.
+    '''
+    max_origin = box.side_length - dimension
+    if max_origin < 0:
+      return []
+    return sorted(set(range(0, max_origin + 1, step)) | {box.snap_to_grid(max_origin)})
+
+  @classmethod
+  def generate_moves_for_rects(
+      cls,
+      solution: BoxSolution,
+      ids: list[tuple[int, int]],
+      step: int,
+      stats: NeighborhoodStats
+    ) -> list[ScoredMove]:
+    '''
+    Generates a list of scored moves for the given rects in `solution` on a grid with the given step,
+    counting their candidates in `stats`.
+    IDs must be given as a list `(box_id, rect_id)`. Stops at the first improving move.
+    '''
+    current_score = cls.generate_heuristic(solution)
+    moves = []
+
+    for (box_id, rect_id) in ids:
+      current_box = solution.boxes[box_id]
+      current_rect = current_box.rects[rect_id]
+
+      # ... at any rotation (no flip if the rect is square)
+      orientations = [(current_rect.width, current_rect.height)]
+      if current_rect.width != current_rect.height:
+        orientations.append((current_rect.height, current_rect.width))
+
+      for possible_box in list(solution.boxes.values()):
+        for (width, height) in orientations:
+          for x in cls.grid_origins(possible_box, width, step):
+            for y in cls.grid_origins(possible_box, height, step):
+              # Off the grid of the box
+              if not possible_box.is_on_grid(x, y):
+                stats.count(False)
+                continue
+
+              # No move
+              if all([
+                current_box.id == possible_box.id,
+                current_rect.get_x() == x,
+                current_rect.get_y() == y,
+                current_rect.width == width
+              ]):
+                continue
+
+              move = GeometricMove(current_rect.id, current_box.id, possible_box.id, x, y, width, height)
+              score = cls.generate_heuristic(solution, move)
+
+              # Skip invalid moves
+              if not cls.check_candidate(score, stats):
+                continue
+
+              moves.append(ScoredMove(move, score))
+
+              # If we found a better solution, return it
+              if score < current_score:
+                return moves
+    return moves
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a solution by moving every rect to every coordinate on the current grid.
+    If none of them improves the solution, the grid is refined and the neighbors are generated again.
+    '''
+    rects = [
+      (box_id, rect_id)
+      for box_id, box in solution.boxes.items()
+      for rect_id in box.rects.keys()
+      if rect_id not in solution.last_moved_rect_ids
+    ]
+    # Like in `Geometric`, rects which are alone in their box are tried first in hopes of emptying it
+    prio_rects = [(box_id, rect_id) for (box_id, rect_id) in rects if len(solution.rectangles_in_box(box_id)) == 1]
+    current_score = cls.generate_heuristic(solution)
+
+    while True:
+      logger.info("Calculating CoarseToFine neighborhood with step %i", cls.step)
+      scored_moves = []
+      if len(prio_rects) > 0:
+        scored_moves = cls.generate_moves_for_rects(solution, prio_rects[:1], cls.step, stats)
+      if not any(m.score < current_score for m in scored_moves):
+        scored_moves = cls.generate_moves_for_rects(solution, rects, cls.step, stats)
+      improving = any(m.score < current_score for m in scored_moves)
+
+      if improving or cls.step == 1:
+        break
+
+      # Converged at this step, so make the grid finer
+      cls.step = max(1, cls.step // cls.refinement_factor)
+      logger.info("Refining grid step to %i", cls.step)
+
+    cls.converged = not improving
+    logger.info("Explored %i neighbors", len(scored_moves))
+    return scored_moves
+
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''Every grid placement of every rect in every box in both orientations at the current step'''
+    def origin_count(box: Box, dimension: int) -> int:
+      return sum(1 for o in cls.grid_origins(box, dimension, cls.step) if box.is_on_grid(o, 0))
+
+    size = 0
+    for box in solution.boxes.values():
+      for rect in box.rects.values():
+        orientations = {(rect.width, rect.height), (rect.height, rect.width)}
+        size += sum(
+          origin_count(possible_box, width) * origin_count(possible_box, height)
+          for possible_box in solution.boxes.values()
+          for (width, height) in orientations
+        )
+    return size
@@ -0,0 +1,99 @@
+from __future__ import annotations
+import logging
+
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.geometry import Box, Rectangle
+
+from .neighborhood import Neighborhood, NeighborhoodStats
+from .geometric import GeometricMove
+from ..move import ScoredMove
+
+logger = logging.getLogger(__name__)
+
+class Compact(Neighborhood):
+  '''
+  Neighborhood which pushes rects against their neighbors instead of trying every coordinate.
+  Every rect is pushed as far left (towards x = 0) and as far down (towards y = 0) as it goes,
+  until it hits another rect or the box border. This closes gaps left by free positioning
+  and is much smaller than the full geometric neighborhood.
+  '''
+
+  @staticmethod
+  def push(
+      free_coords: set[tuple[int, int]],
+      origin: tuple[int, int],
+      size: tuple[int, int],
+      direction: tuple[int, int],
+      step: int = 1
+    ) -> tuple[int, int]:
+    '''
+    Returns the origin a rect of the given size ends up at when it is pushed from `origin`
+    in `direction` (`(-1, 0)` or `(0, -1)`) in steps of the box's grid unit, as long as it only covers free coordinates.
+    '''
+    (x, y), (width, height), (dx, dy) = origin, size, direction
+    while True:
+      next_x, next_y = x + dx * step, y + dy * step
+      if next_x < 0 or next_y < 0:
+        break
+      # Only the strip the rect moves into needs to be free
+      if dx != 0:
+        strip = {(sx, sy) for sx in range(next_x, x) for sy in range(y, y + height)}
+      else:
+        strip = {(sx, sy) for sx in range(x, x + width) for sy in range(next_y, y)}
+      if not strip <= free_coords:
+        break
+      x, y = next_x, next_y
+    return (x, y)
+
+  @classmethod
+  def pushed_origins(cls, box: Box, rect: Rectangle) -> list[tuple[int, int]]:
+    '''
+    Returns the distinct origins of pushing a rect only left, only down and both in turn until it is stuck.
+    The current origin of the rect is not part of them.
+    '''
+    # The rect doesn't block itself
+    free_coords = box.get_free_coordinates() | rect.get_all_coordinates()
+    origin = (rect.get_x(), rect.get_y())
+    size = (rect.get_width(), rect.get_height())
+    left = cls.push(free_coords, origin, size, (-1, 0), box.grid_unit)
+    down = cls.push(free_coords, origin, size, (0, -1), box.grid_unit)
+
+    # Pushing left can open up room below and the other way round, so keep going until nothing moves
+    corner = origin
+    while True:
+      pushed_down = cls.push(free_coords, corner, size, (0, -1), box.grid_unit)
+      pushed = cls.push(free_coords, pushed_down, size, (-1, 0), box.grid_unit)
+      if pushed == corner:
+        break
+      corner = pushed
+
+    origins = []
+    for candidate in [left, down, corner]:
+      if candidate != origin and candidate not in origins:
+        origins.append(candidate)
+    return origins
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a solution by pushing every rect left, down and into the closest corner.
+    '''
+    logger.info("Calculating Compact neighborhood")
+
+    moves = []
+    for box in solution.boxes.values():
+      for rect in list(box.rects.values()):
+        # If rect was recently moved, ignore it
+        if rect.id in solution.last_moved_rect_ids:
+          continue
+        for (x, y) in cls.pushed_origins(box, rect):
+          moves.append(GeometricMove(rect.id, box.id, box.id, x, y, rect.get_width(), rect.get_height()))
+    scored_moves = cls.evaluate_moves(solution, moves, stats)
+
+    logger.info("Explored %i neighbors", len(scored_moves))
+    return scored_moves
+
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''At most three pushes per rect'''
+    return 3 * sum(len(box.rects) for box in solution.boxes.values())
@@ -0,0 +1,101 @@
+from __future__ import annotations
+import logging
+from dataclasses import dataclass, field
+
+from problem.box_problem.box_solution import BoxSolution
+
+from .neighborhood import Neighborhood, NeighborhoodStats
+from ..move import Move, ScoredMove
+
+logger = logging.getLogger(__name__)
+
+class DissolveBox(Neighborhood):
+  '''
+  Neighborhood which tries to empty whole boxes at once.
+  For every box, all of its rects are distributed into the remaining boxes,
+  which single rect moves can not achieve in one step. Only neighbors where
+  all rects found a new place are kept, so every neighbor has one box less.
+  '''
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a solution by trying to dissolve every box into the others.
+    '''
+    logger.info("Calculating DissolveBox neighborhood")
+
+    # Dissolving the only box there is won't work
+    if len(solution.boxes) < 2:
+      return []
+
+    moves = [DissolveBoxMove(box_id) for box_id in list(solution.boxes.keys())]
+    scored_moves = cls.evaluate_moves(solution, moves, stats)
+
+    logger.info("Explored %i neighbors", len(scored_moves))
+    return scored_moves
+
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''One candidate per box'''
+    return len(solution.boxes) if len(solution.boxes) >= 2 else 0
+
+@dataclass
+class DissolveBoxMove(Move):
+  '''
+  Defines a move as emptying one box by placing each of its rects into one of the other boxes.
+  The rects are placed in order of decreasing area at the first spot they fit in.
+  '''
+  box_id: int
+
+  placements: list[tuple[int, int, int, int, bool]] = field(default_factory=list)
+  '''Record of `(rect_id, to_box_id, old_x, old_y, flipped)` for undoing the move'''
+  box_order: list[int] = field(default_factory=list)
+  '''Order of the box ids before the move, so undo can restore it'''
+
+  def apply_to_solution(self, solution: BoxSolution) -> bool:
+    '''
+    Tries to apply this move to a given box solution.
+    Will return false and leave the solution untouched if not all rects could be placed.
+    '''
+    self.placements = []
+    self.box_order = list(solution.boxes.keys())
+    box = solution.boxes.pop(self.box_id)
+    rects = sorted(box.rects.values(), key=lambda r: r.get_area(), reverse=True)
+
+    for rect in rects:
+      old_x, old_y, old_width = rect.get_x(), rect.get_y(), rect.get_width()
+      box.remove_rect(rect.id)
+
+      # Find the first box that takes the rect
+      target_box = next((b for b in solution.boxes.values() if b.fit_rect_compress(rect)), None)
+
+      if target_box is None:
+        # Put the rect back and revert everything placed so far
+        box.add_rect(rect)
+        solution.boxes[self.box_id] = box
+        self.undo(solution)
+        return False
+
+      rect.highlighted = True
+      self.placements.append((rect.id, target_box.id, old_x, old_y, rect.get_width() != old_width))
+
+    return True
+
+  def undo(self, solution: BoxSolution):
+    '''Undoes whatever this move had done to the argument solution'''
+    # Either the box was never removed or it was restored by a failed apply
+    box = solution.boxes.pop(self.box_id, None)
+    if box is None:
+      box = solution.new_box(self.box_id)
+
+    for (rect_id, to_box_id, old_x, old_y, flipped) in reversed(self.placements):
+      rect = solution.boxes[to_box_id].remove_rect(rect_id)
+      rect.move_to(old_x, old_y)
+      if flipped:
+        rect.flip()
+      rect.highlighted = False
+      box.add_rect(rect)
+
+    self.placements = []
+    solution.boxes[self.box_id] = box
+    solution.boxes = { box_id: solution.boxes[box_id] for box_id in self.box_order }
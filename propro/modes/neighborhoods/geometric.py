@@ -1,19 +1,12 @@
 from __future__ import annotations
 import logging
 from dataclasses import dataclass
-from multiprocessing import Pool, cpu_count
-from copy import deepcopy
+from multiprocessing import cpu_count
 import os
 
-import numpy as np
-
 from problem.box_problem.box_solution import BoxSolution
-from problem.box_problem.geometry import Box
-from problem.box_problem.box_heuristic import GenericHeuristic
-from utils import flatten
-
 
-from .neighborhood import Neighborhood
+from .neighborhood import Neighborhood, NeighborhoodStats
 from ..move import Move, ScoredMove
 
 logger = logging.getLogger(__name__)
@@ -26,10 +19,21 @@ class Geometric(Neighborhood):
   n_proc = max(int(os.environ.get("OPTALGO_MAX_CPU", 0)), cpu_count())
   '''Number of processes the neighborhood searching should use'''
 
+  largest_first = False
+  '''
+  Whether to try moving the rects in order of descending area instead of storage order.
+  Large rects tend to find box-reducing moves sooner, the set of generated moves stays the same.
+  '''
+
   @classmethod
-  def generate_moves_for_rects(cls, solution: BoxSolution, ids: list[tuple[int, int]]) -> list[ScoredMove]:
+  def generate_moves_for_rects(
+      cls,
+      solution: BoxSolution,
+      ids: list[tuple[int, int]],
+      stats: NeighborhoodStats
+    ) -> list[ScoredMove]:
     '''
-    Generates a list of scoreed moves for the given rects in `solution`.
+    Generates a list of scoreed moves for the given rects in `solution`, counting their candidates in `stats`.
     IDs must be given as a list `(bod_id, rect_id)`.
     '''
     current_score = cls.generate_heuristic(solution)
@@ -43,16 +47,16 @@ class Geometric(Neighborhood):
       for possible_box in list(solution.boxes.values()):
         # ... in any free coordinate within this box
         for (x, y) in list(possible_box.get_adjacent_coordinates()):
-          # ... at any rotation
-          for is_flipped in [False, True]:
-
-            # Rect would overflow
-            if any([
-              not is_flipped and (x + current_rect.width > possible_box.side_length),
-              not is_flipped and (y + current_rect.height > possible_box.side_length),
-              is_flipped and (y + current_rect.width > possible_box.side_length),
-              is_flipped and (x + current_rect.height > possible_box.side_length),
-            ]):
+          # ... at any rotation (no flip if the rect is square)
+          orientations = [(current_rect.width, current_rect.height)]
+          if current_rect.width != current_rect.height:
+            orientations.append((current_rect.height, current_rect.width))
+          for (width, height) in orientations:
+
+            # Rect would be off the grid or overflow
+            if not possible_box.is_on_grid(x, y) or \
+              x + width > possible_box.side_length or y + height > possible_box.side_length:
+              stats.count(False)
               continue
 
             # No move
@@ -60,19 +64,15 @@ class Geometric(Neighborhood):
               current_box.id == possible_box.id,
               current_rect.get_x() == x,
               current_rect.get_y() == y,
-              not is_flipped
+              current_rect.width == width
             ]):
               continue
 
-            # No flip if the rect is square
-            if current_rect.width == current_rect.height and is_flipped:
-              continue
-
-            move = GeometricMove(current_rect.id, current_box.id, possible_box.id, x, y, is_flipped)
+            move = GeometricMove(current_rect.id, current_box.id, possible_box.id, x, y, width, height)
             score = cls.generate_heuristic(solution, move)
 
             # Skip invalid moves
-            if not score.is_valid():
+            if not cls.check_candidate(score, stats):
               continue
 
             moves.append(ScoredMove(move, score))
@@ -82,13 +82,14 @@ class Geometric(Neighborhood):
               return moves
       # Bonus move! Put the rect into a new box at 0/0
       new_box_id = max(solution.boxes.keys()) + 1
-      new_box_move = GeometricMove(rect_id, box_id, new_box_id, 0, 0, False)
+      new_box_move = GeometricMove(rect_id, box_id, new_box_id, 0, 0, current_rect.width, current_rect.height)
       new_box_score = cls.generate_heuristic(solution, new_box_move)
-      moves.append(ScoredMove(new_box_move, new_box_score))
+      if cls.check_candidate(new_box_score, stats):
+        moves.append(ScoredMove(new_box_move, new_box_score))
     return moves
 
   @classmethod
-  def get_neighbors(cls, solution: BoxSolution) -> list[ScoredMove]:
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
     '''
     Calculates neighbors of a solution by geometric means
     Moves every rectangle in every box to every possible coordinate
@@ -116,15 +117,18 @@ class Geometric(Neighborhood):
         else:
           rects.append((box_id, rect_id))
 
+    if cls.largest_first:
+      rects.sort(key=lambda ids: solution.boxes[ids[0]].rects[ids[1]].get_area(), reverse=True)
+
     # If we have a prio rect, generate moves only for this in hopes of
     # putting it into another box
     scored_moves = []
 
     if prio_rect is not None:
-      scored_moves = cls.generate_moves_for_rects(solution, [prio_rect])
+      scored_moves = cls.generate_moves_for_rects(solution, [prio_rect], stats)
 
     if len(scored_moves) == 0:
-      scored_moves = cls.generate_moves_for_rects(solution, rects)
+      scored_moves = cls.generate_moves_for_rects(solution, rects, stats)
 
     # # If scored moves are empty either because there was no prio rect or because
     # # the method didn't return any valid neighbors, do the expensive shaboingboing
@@ -145,32 +149,19 @@ class Geometric(Neighborhood):
     return scored_moves
 
   @classmethod
-  def generate_heuristic(cls, solution: BoxSolution, move: GeometricMove = None) -> GenericHeuristic:
+  def estimate_size(cls, solution: BoxSolution) -> int:
     '''
-    Calculates the heuristic score of the solution after a given move.
-    Passing no move will return the heuristic score of the solution itself.
+    Counts every orientation of every rect at every adjacent coordinate of every box, which is what gets enumerated.
+    The rect's own position is counted as well, which stands in for the new box move.
+    This is an upper bound, since generation stops at the first improving move.
     '''
-    move_sucessful = True
-
-    # Perform move
-    if move is not None:
-      move_sucessful = move.apply_to_solution(solution)
-
-    # If move was unsuccessful, it resulted in an invalid solution
-    if not move_sucessful:
-      return GenericHeuristic(None, None, None)
-
-    # If move is valid, construct a proper score,
-    heuristic = GenericHeuristic(
-      len(solution.boxes),
-      solution.compute_box_entropy(),
-      solution.compute_incident_edge_coordinates()
+    coordinate_count = sum(len(box.get_adjacent_coordinates()) for box in solution.boxes.values())
+    orientation_count = sum(
+      1 if rect.width == rect.height else 2
+      for box in solution.boxes.values()
+      for rect in box.rects.values()
     )
-
-    # Undo the move operation
-    if move is not None:
-      move.undo(solution)
-    return heuristic
+    return coordinate_count * orientation_count
 
 @dataclass
 class GeometricMove(Move):
@@ -180,20 +171,36 @@ class GeometricMove(Move):
   to_box_id: int
   new_x: int
   new_y: int
-  flip: bool
+  new_width: int
+  '''Width of the rect at its target position, the rect is flipped if needed'''
+  new_height: int
+  '''Height of the rect at its target position'''
 
   old_x: int
   old_y: int
-
-  def __init__(self, rect_id: int, from_box_id: int, to_box_id:int, new_x: int, new_y: int, flip: bool):
+  flip: bool
+  '''Whether applying the move had to flip the rect'''
+
+  def __init__(
+      self,
+      rect_id: int,
+      from_box_id: int,
+      to_box_id:int,
+      new_x: int,
+      new_y: int,
+      new_width: int,
+      new_height: int
+    ):
     self.rect_id = rect_id
     self.from_box_id = from_box_id
     self.to_box_id = to_box_id
     self.new_x = new_x
     self.new_y = new_y
-    self.flip = flip
+    self.new_width = new_width
+    self.new_height = new_height
     self.old_x = None
     self.old_y = None
+    self.flip = False
 
   def apply_to_solution(self, solution: BoxSolution) -> bool:
     '''
@@ -209,10 +216,10 @@ class GeometricMove(Move):
     self.old_x = current_rect.get_x()
     self.old_y = current_rect.get_y()
 
-    # Update rect coordinates
+    # Bring the rect into its target orientation before placing it,
+    # so the placement is checked with the same dimensions the move was generated for
+    self.flip = current_rect.orient(self.new_width, self.new_height)
     current_rect.move_to(self.new_x, self.new_y)
-    if self.flip:
-      current_rect.flip()
 
     # Check if new box id is already in solution
     if self.to_box_id in solution.boxes:
@@ -230,7 +237,7 @@ class GeometricMove(Move):
 
     # If it was not, create a new box
     else:
-      new_box = Box(self.to_box_id, current_box.side_length, current_rect)
+      new_box = solution.new_box(self.to_box_id, current_rect)
       solution.boxes[self.to_box_id] = new_box
 
     # Highlight it as changed
@@ -243,6 +250,11 @@ class GeometricMove(Move):
     if len(current_box.rects) == 0:
       solution.boxes.pop(self.from_box_id)
 
+    # Opening a box is only allowed if there is one left
+    if solution.exceeds_max_boxes():
+      self.undo(solution)
+      return False
+
     return True
 
   def undo(self, solution: BoxSolution):
@@ -268,7 +280,7 @@ class GeometricMove(Move):
 
     # Maybe the old box was deleted by the move? Otherwise just add it back
     if self.from_box_id not in solution.boxes.keys():
-      solution.boxes[self.from_box_id] = Box(self.from_box_id, solution.side_length, rect)
+      solution.boxes[self.from_box_id] = solution.new_box(self.from_box_id, rect)
     else:
       # solution.boxes[self.from_box_id].needs_redraw = True
       solution.boxes[self.from_box_id].add_rect(rect)
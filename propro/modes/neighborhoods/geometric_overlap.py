@@ -9,11 +9,10 @@ from dataclasses import dataclass
 import numpy as np
 
 from problem.box_problem.box_solution import BoxSolution
-from problem.box_problem.geometry import Box
 from problem.box_problem.box_heuristic import OverlapHeuristic
 from utils import flatten
 
-from .neighborhood import Neighborhood
+from .neighborhood import Neighborhood, NeighborhoodStats
 from ..move import ScoredMove, Move
 
 logger = logging.getLogger(__name__)
@@ -32,6 +31,16 @@ class GeometricOverlap(Neighborhood):
   call_count = 0
   '''Keeps track of how many times `get_neighbors` was called'''
 
+  cacheable = False
+  '''Lowers the permissible overlap while generating neighbors'''
+
+  overlap_penalty = 0.0
+  '''
+  Weight of the overlap area in the score, where 1 makes a box worth of overlapping area as bad as a box.
+  Higher values push the search out of overlapping packings sooner, 0 only counts boxes.
+  Can be changed while the search runs, it is read on every scoring.
+  '''
+
   # TODO: collapses just fine, but can't build up again..
 
   @classmethod
@@ -42,14 +51,14 @@ class GeometricOverlap(Neighborhood):
     solution.currently_permissible_overlap = 1.0
 
     # Create a new box with no advanced coordinate features
-    new_box = Box(0, solution.side_length, calc_coords=False)
+    new_box = solution.new_box(0, calc_coords=False)
 
     # Move all rects to this box
     for box_id in list(solution.boxes.keys()):
       box = solution.boxes.pop(box_id)
       # Move all rects
       for rect_id in list(box.rects.keys()):
-        rect = box.rects.pop(rect_id)
+        rect = box.remove_rect(rect_id)
         rect.move_to(0, 0)
         new_box.add_rect(rect)
 
@@ -58,9 +67,14 @@ class GeometricOverlap(Neighborhood):
     return solution
 
   @classmethod
-  def generate_moves_for_rects(cls, solution: BoxSolution, ids: list[tuple[int, int]]) -> list[ScoredMove]:
+  def generate_moves_for_rects(
+      cls,
+      solution: BoxSolution,
+      ids: list[tuple[int, int]],
+      stats: NeighborhoodStats
+    ) -> list[ScoredMove]:
     '''
-    Generates a list of scoreed moves for the given rects in `solution`.
+    Generates a list of scoreed moves for the given rects in `solution`, counting their candidates in `stats`.
     IDs must be given as a list `(bod_id, rect_id)`.
     '''
     current_score = cls.generate_heuristic(solution)
@@ -72,18 +86,20 @@ class GeometricOverlap(Neighborhood):
 
       # Iterate over every target box
       for possible_box in list(solution.boxes.values()):
-        # ... in any free coordinate within this box
-        for (x, y) in product(range(possible_box.side_length - current_rect.width), range(possible_box.side_length - current_rect.height)):
-          # ... at any rotation
-          for is_flipped in [False, True]:
+        # ... in any coordinate within this box where the rect could fit in either orientation
+        # (a rect may sit flush against the edge, so the last origin is side_length - dimension)
+        max_origin = possible_box.side_length - min(current_rect.width, current_rect.height)
+        origins = range(0, max_origin + 1, possible_box.grid_unit)
+        for (x, y) in product(origins, origins):
+          # ... at any rotation (no flip if the rect is square)
+          orientations = [(current_rect.width, current_rect.height)]
+          if current_rect.width != current_rect.height:
+            orientations.append((current_rect.height, current_rect.width))
+          for (width, height) in orientations:
 
             # Rect would overflow
-            if any([
-              not is_flipped and (x + current_rect.width > possible_box.side_length),
-              not is_flipped and (y + current_rect.height > possible_box.side_length),
-              is_flipped and (y + current_rect.width > possible_box.side_length),
-              is_flipped and (x + current_rect.height > possible_box.side_length),
-            ]):
+            if x + width > possible_box.side_length or y + height > possible_box.side_length:
+              stats.count(False)
               continue
 
             # No move
@@ -91,18 +107,14 @@ class GeometricOverlap(Neighborhood):
               current_box.id == possible_box.id,
               current_rect.get_x() == x,
               current_rect.get_y() == y,
-              not is_flipped
+              current_rect.width == width
             ]):
               continue
 
-            # No flip if the rect is square
-            if current_rect.width == current_rect.height and is_flipped:
-              continue
-
-            move = GeometricOverlapMove(current_rect.id, current_box.id, possible_box.id, x, y, is_flipped)
+            move = GeometricOverlapMove(current_rect.id, current_box.id, possible_box.id, x, y, width, height)
             score = cls.generate_heuristic(solution, move)
             # Skip invalid moves
-            if not score.is_valid():
+            if not cls.check_candidate(score, stats):
               continue
 
             moves.append(ScoredMove(move, score))
@@ -112,13 +124,28 @@ class GeometricOverlap(Neighborhood):
               return moves
       # Bonus move! Put the rect into a new box at 0/0
       new_box_id = max(solution.boxes.keys()) + 1
-      new_box_move = GeometricOverlapMove(rect_id, box_id, new_box_id, 0, 0, False)
+      new_box_move = GeometricOverlapMove(rect_id, box_id, new_box_id, 0, 0, current_rect.width, current_rect.height)
       new_box_score = cls.generate_heuristic(solution, new_box_move)
-      moves.append(ScoredMove(new_box_move, new_box_score))
+      if cls.check_candidate(new_box_score, stats):
+        moves.append(ScoredMove(new_box_move, new_box_score))
     return moves
 
   @classmethod
-  def get_neighbors(cls, solution: BoxSolution) -> list[ScoredMove]:
+  def generate_counted_moves_for_rects(
+      cls,
+      solution: BoxSolution,
+      ids: list[tuple[int, int]]
+    ) -> tuple[list[ScoredMove], NeighborhoodStats]:
+    '''
+    Generates the moves like `generate_moves_for_rects` and also returns the counts of their candidates,
+    since a worker process can't count them in the stats of the main one
+    '''
+    stats = NeighborhoodStats()
+    moves = cls.generate_moves_for_rects(solution, ids, stats)
+    return (moves, stats)
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
     '''
     Calculates neighbors of a solution by geometric means
     Moves every rectangle in every box to every possible coordinate
@@ -155,28 +182,56 @@ class GeometricOverlap(Neighborhood):
 
       # Evaluate all rects to scored moves concurrently
       with Pool(processes=cls.n_proc) as pool:
-        scored_moves = flatten(pool.starmap(cls.generate_moves_for_rects, zip(solution_copies, chunks)))
+        results = pool.starmap(cls.generate_counted_moves_for_rects, zip(solution_copies, chunks))
+      scored_moves = flatten([moves for (moves, _) in results])
+      for (_, worker_stats) in results:
+        stats.add(worker_stats)
     # Else just do it here
     else:
-      scored_moves = cls.generate_moves_for_rects(solution, rects)
+      scored_moves = cls.generate_moves_for_rects(solution, rects, stats)
 
     logger.info("Explored %i neighbors", len(scored_moves))
     return scored_moves
 
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''Counts every in-bounds placement of every rect in every box in both orientations, plus a new box each'''
+    size = 0
+    for box in solution.boxes.values():
+      for rect in box.rects.values():
+        # Number of origins per axis for both orientations
+        placements = (solution.side_length - rect.width + 1) * (solution.side_length - rect.height + 1)
+        if rect.width != rect.height:
+          placements *= 2
+        size += len(solution.boxes) * placements
+    return size
+
   @classmethod
   def generate_heuristic(cls, solution: BoxSolution, move: Move = None) -> OverlapHeuristic:
     '''
     Calculates the heuristic score of the solution after a given move.
     Passing no move will return the heuristic score of the solution itself.
     '''
+    move_successful = True
+
     # Perform move
     if move is not None:
-      move.apply_to_solution(solution)
+      move_successful = move.apply_to_solution(solution)
+
+    # If move was unsuccessful, it resulted in an invalid solution
+    if not move_successful:
+      return OverlapHeuristic(None, None, None)
+
+    # Only pay for computing the overlap area if it is weighted at all
+    overlap_penalty = 0.0
+    if cls.overlap_penalty > 0:
+      overlap_penalty = cls.overlap_penalty * solution.compute_overlap_area() / solution.side_length**2
 
     heuristic = OverlapHeuristic(
       len(solution.boxes),
       solution.count_illegal_overlaps(),
-      solution.compute_incident_edge_coordinates()
+      solution.compute_incident_edge_coordinates(),
+      overlap_penalty
     )
 
     # Undo the move operation
@@ -192,20 +247,36 @@ class GeometricOverlapMove(Move):
   to_box_id: int
   new_x: int
   new_y: int
-  flip: bool
+  new_width: int
+  '''Width of the rect at its target position, the rect is flipped if needed'''
+  new_height: int
+  '''Height of the rect at its target position'''
 
   old_x: int
   old_y: int
-
-  def __init__(self, rect_id: int, from_box_id: int, to_box_id:int, new_x: int, new_y: int, flip: bool):
+  flip: bool
+  '''Whether applying the move had to flip the rect'''
+
+  def __init__(
+      self,
+      rect_id: int,
+      from_box_id: int,
+      to_box_id:int,
+      new_x: int,
+      new_y: int,
+      new_width: int,
+      new_height: int
+    ):
     self.rect_id = rect_id
     self.from_box_id = from_box_id
     self.to_box_id = to_box_id
     self.new_x = new_x
     self.new_y = new_y
-    self.flip = flip
+    self.new_width = new_width
+    self.new_height = new_height
     self.old_x = None
     self.old_y = None
+    self.flip = False
 
   def apply_to_solution(self, solution: BoxSolution) -> bool:
     '''
@@ -221,10 +292,10 @@ class GeometricOverlapMove(Move):
     self.old_x = current_rect.get_x()
     self.old_y = current_rect.get_y()
 
-    # Update rect coordinates
+    # Bring the rect into its target orientation before placing it,
+    # so the placement is checked with the same dimensions the move was generated for
+    self.flip = current_rect.orient(self.new_width, self.new_height)
     current_rect.move_to(self.new_x, self.new_y)
-    if self.flip:
-      current_rect.flip()
 
     # Check if new box id is already in solution
     if self.to_box_id in solution.boxes:
@@ -242,7 +313,7 @@ class GeometricOverlapMove(Move):
 
     # If it was not, create a new box
     else:
-      new_box = Box(self.to_box_id, current_box.side_length, current_rect, calc_coords=False)
+      new_box = solution.new_box(self.to_box_id, current_rect, calc_coords=False)
       solution.boxes[self.to_box_id] = new_box
 
     # Highlight it as changed
@@ -255,6 +326,11 @@ class GeometricOverlapMove(Move):
     if len(current_box.rects) == 0:
       solution.boxes.pop(self.from_box_id)
 
+    # Opening a box is only allowed if there is one left
+    if solution.exceeds_max_boxes():
+      self.undo(solution)
+      return False
+
     return True
 
   def undo(self, solution: BoxSolution):
@@ -280,7 +356,7 @@ class GeometricOverlapMove(Move):
 
     # Maybe the old box was deleted by the move? Otherwise just add it back
     if self.from_box_id not in solution.boxes.keys():
-      solution.boxes[self.from_box_id] = Box(self.from_box_id, solution.side_length, rect, calc_coords=False)
+      solution.boxes[self.from_box_id] = solution.new_box(self.from_box_id, rect, calc_coords=False)
     else:
       # solution.boxes[self.from_box_id].needs_redraw = True
       solution.boxes[self.from_box_id].add_rect(rect)
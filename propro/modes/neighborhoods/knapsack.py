@@ -0,0 +1,226 @@
+from __future__ import annotations
+import logging
+from dataclasses import dataclass
+
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
+from problem.box_problem.box_heuristic import KnapsackHeuristic
+
+from .neighborhood import Neighborhood, NeighborhoodStats
+from ..move import Move, ScoredMove
+
+logger = logging.getLogger(__name__)
+
+class Knapsack(Neighborhood):
+  '''
+  Neighborhood for the knapsack variant of the box-rect problem.
+  There is a fixed number of boxes and rects may be left out of the packing,
+  the objective is to maximize the summed value of all packed rects.
+  Neighbors are found by moving rects from the pool of unplaced rects into a box
+  or by taking them out of their box into the pool again.
+  '''
+
+  box_count = 1
+  '''Number of fixed boxes that are available for packing'''
+
+  @classmethod
+  def initialize(cls, solution: BoxSolution) -> BoxSolution:
+    '''Initializes the solution by moving all rects into the unplaced pool and leaving only empty boxes'''
+    for box in solution.boxes.values():
+      for rect_id in list(box.rects.keys()):
+        rect = box.remove_rect(rect_id)
+        rect.move_to(0, 0, box_id=UNPLACED_BOX_ID)
+        solution.unplaced_rects[rect.id] = rect
+
+    box_count = cls.box_count if solution.max_boxes is None else min(cls.box_count, solution.max_boxes)
+    solution.boxes = { i: solution.new_box(i) for i in range(box_count) }
+    return solution
+
+  @classmethod
+  def generate_neighbor_moves(cls, solution: BoxSolution) -> list[KnapsackMove]:
+    '''Generates all moves into and out of the unplaced pool'''
+    moves = []
+
+    # Try to pack every unplaced rect into every box
+    for rect in solution.unplaced_rects.values():
+      if rect.id in solution.last_moved_rect_ids:
+        continue
+      for box in solution.boxes.values():
+        for (x, y) in [c for c in box.get_adjacent_coordinates() if box.is_on_grid(*c)]:
+          # ... at any rotation (no flip if the rect is square)
+          orientations = [(rect.width, rect.height)]
+          if rect.width != rect.height:
+            orientations.append((rect.height, rect.width))
+          for (width, height) in orientations:
+            # Rect would overflow
+            if x + width > box.side_length or y + height > box.side_length:
+              continue
+
+            moves.append(KnapsackMove(rect.id, UNPLACED_BOX_ID, box.id, x, y, width, height))
+
+    # Try to take every placed rect out of its box
+    for box in solution.boxes.values():
+      for rect_id in box.rects.keys():
+        if rect_id in solution.last_moved_rect_ids:
+          continue
+        rect = box.rects[rect_id]
+        moves.append(KnapsackMove(rect_id, box.id, UNPLACED_BOX_ID, 0, 0, rect.width, rect.height))
+
+    return moves
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a solution by packing unplaced rects at any adjacent coordinate
+    of any box or removing packed rects from their box.
+    '''
+    logger.info("Calculating Knapsack neighborhood")
+
+    moves = cls.generate_neighbor_moves(solution)
+    scored_moves = cls.evaluate_moves(solution, moves, stats)
+
+    logger.info("Explored %i neighbors", len(scored_moves))
+    return scored_moves
+
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''Every unplaced rect at every adjacent coordinate in both orientations, plus taking out every placed rect'''
+    adjacent_coordinates = sum(len(b.get_adjacent_coordinates()) for b in solution.boxes.values())
+    size = sum(
+      adjacent_coordinates * (1 if rect.width == rect.height else 2)
+      for rect in solution.unplaced_rects.values()
+    )
+    size += sum(len(b.rects) for b in solution.boxes.values())
+    return size
+
+  @classmethod
+  def generate_heuristic(cls, solution: BoxSolution, move: KnapsackMove = None) -> KnapsackHeuristic:
+    '''
+    Calculates the heuristic score of the solution after a given move.
+    Passing no move will return the heuristic score of the solution itself.
+    '''
+    move_successful = True
+
+    # Perform move
+    if move is not None:
+      move_successful = move.apply_to_solution(solution)
+
+    # If move was unsuccessful, it resulted in an invalid solution
+    if not move_successful:
+      return KnapsackHeuristic(None, None)
+
+    heuristic = KnapsackHeuristic(
+      solution.compute_placed_value(),
+      solution.compute_incident_edge_coordinates()
+    )
+
+    # Undo the move operation
+    if move is not None:
+      move.undo(solution)
+    return heuristic
+
+@dataclass
+class KnapsackMove(Move):
+  '''
+  Defines a move of a rectangle between a box and the unplaced pool.
+  A box id of `UNPLACED_BOX_ID` refers to the pool.
+  '''
+  rect_id: int
+  from_box_id: int
+  to_box_id: int
+  new_x: int
+  new_y: int
+  new_width: int
+  '''Width of the rect at its target position, the rect is flipped if needed'''
+  new_height: int
+  '''Height of the rect at its target position'''
+
+  old_x: int
+  old_y: int
+  flip: bool
+  '''Whether applying the move had to flip the rect'''
+
+  def __init__(
+      self,
+      rect_id: int,
+      from_box_id: int,
+      to_box_id:int,
+      new_x: int,
+      new_y: int,
+      new_width: int,
+      new_height: int
+    ):
+    self.rect_id = rect_id
+    self.from_box_id = from_box_id
+    self.to_box_id = to_box_id
+    self.new_x = new_x
+    self.new_y = new_y
+    self.new_width = new_width
+    self.new_height = new_height
+    self.old_x = None
+    self.old_y = None
+    self.flip = False
+
+  @staticmethod
+  def __take_rect(solution: BoxSolution, box_id: int, rect_id: int):
+    '''Removes a rect from a box or the unplaced pool'''
+    if box_id == UNPLACED_BOX_ID:
+      return solution.unplaced_rects.pop(rect_id)
+    return solution.boxes[box_id].remove_rect(rect_id)
+
+  @staticmethod
+  def __put_rect(solution: BoxSolution, box_id: int, rect) -> bool:
+    '''Adds a rect to a box or the unplaced pool'''
+    if box_id == UNPLACED_BOX_ID:
+      rect.box_id = UNPLACED_BOX_ID
+      solution.unplaced_rects[rect.id] = rect
+      return True
+    return solution.boxes[box_id].add_rect(rect)
+
+  def apply_to_solution(self, solution: BoxSolution) -> bool:
+    '''
+    Tries to apply this move to a given box solution.
+    Will return false if resulting solution is invalid.
+    '''
+    current_rect = self.__take_rect(solution, self.from_box_id, self.rect_id)
+
+    # Save the old rect coordinates in case of undo
+    self.old_x = current_rect.get_x()
+    self.old_y = current_rect.get_y()
+
+    # Bring the rect into its target orientation before placing it,
+    # so the placement is checked with the same dimensions the move was generated for
+    self.flip = current_rect.orient(self.new_width, self.new_height)
+    current_rect.move_to(self.new_x, self.new_y)
+
+    if not self.__put_rect(solution, self.to_box_id, current_rect):
+      # Revert rect coordinates and put it back where it came from
+      current_rect.move_to(self.old_x, self.old_y)
+      if self.flip:
+        current_rect.flip()
+      self.__put_rect(solution, self.from_box_id, current_rect)
+      return False
+
+    # Highlight it as changed
+    current_rect.highlighted = True
+
+    # Add rect id to problem's last moved queue
+    solution.last_moved_rect_ids.append(current_rect.id)
+    return True
+
+  def undo(self, solution: BoxSolution):
+    '''Undoes whatever this move had done to the argument solution'''
+    if self.old_x is None or self.old_y is None:
+      raise ValueError("Undo called without the move being performed before!")
+
+    rect = self.__take_rect(solution, self.to_box_id, self.rect_id)
+
+    # Restore attributes
+    rect.move_to(self.old_x, self.old_y)
+    if self.flip:
+      rect.flip()
+
+    # Remove it from last moved rect ids again
+    solution.last_moved_rect_ids.pop()
+    rect.highlighted = False
+
+    self.__put_rect(solution, self.from_box_id, rect)
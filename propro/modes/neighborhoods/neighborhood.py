@@ -1,39 +1,159 @@
 '''
 Contains a basic neighborhood definition that must be inherited from
 '''
+from __future__ import annotations
 from abc import abstractmethod
+from copy import deepcopy
+from dataclasses import dataclass
 
 from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_heuristic import GenericHeuristic
 from problem.heuristic import AbstractHeuristic
 from ..move import Move, ScoredMove
 from ..mode import Mode
 
+LARGE_NEIGHBORHOOD_SIZE = 1_000_000
+'''Estimated neighborhood size above which a tick may take minutes'''
+
+@dataclass
+class NeighborhoodStats:
+  '''Counts of the candidate moves that were enumerated, to see how many of them get thrown away as infeasible'''
+  generated: int = 0
+  '''Number of candidates that were enumerated, including the ones skipped before scoring, e.g. off the grid'''
+  feasible: int = 0
+  '''Number of candidates that led to a valid solution and were kept'''
+
+  def count(self, feasible: bool):
+    '''Counts one more candidate'''
+    self.generated += 1
+    if feasible:
+      self.feasible += 1
+
+  def feasible_ratio(self) -> float:
+    '''Returns the fraction of the candidates that were feasible'''
+    return self.feasible / self.generated if self.generated > 0 else 0.0
+
+  def add(self, other: NeighborhoodStats):
+    '''Adds the counts of another one, e.g. of a worker process, to these'''
+    self.generated += other.generated
+    self.feasible += other.feasible
+
+  def __str__(self):
+    return f"{self.feasible:,}/{self.generated:,} feasible ({self.feasible_ratio():.1%})"
+
 class Neighborhood(Mode):
   '''Abstract neighborhood base class'''
 
+  cacheable = True
+  '''
+  Whether the neighbors of a solution only depend on the solution and the settings of this neighborhood.
+  Neighborhoods that change their own state while generating must not be cached.
+  '''
+
+  @staticmethod
+  def check_candidate(score: AbstractHeuristic, stats: NeighborhoodStats) -> bool:
+    '''Returns whether a scored candidate is feasible, counting it in `stats` either way'''
+    feasible = score.is_valid()
+    stats.count(feasible)
+    return feasible
+
+  @classmethod
+  def get_neighbors_with_stats(cls, solution: BoxSolution) -> tuple[list[ScoredMove], NeighborhoodStats]:
+    '''Calculates the neighbors like `get_neighbors`, along with the counts of the candidates enumerated for them'''
+    stats = NeighborhoodStats()
+    neighbors = cls.generate_neighbors(solution, stats)
+    return (neighbors, stats)
+
   #TODO: place the abstract heuristic score stuff here.
   # Now it is in the problem.py. But it is mode-specific, so it should be here.
 
   @classmethod
-  @abstractmethod
-  def evaluate_moves(cls, solution: BoxSolution, moves: list[Move]) -> list[ScoredMove]:
-    '''Takes a list of moves and evaluates them on the given solution.'''
-    # return [
-    #   ScoredMove(move, solution.calculate_heuristic_score(move))
-    #   for move in moves
-    # ]
+  def get_parameters(cls) -> tuple:
+    '''Returns the values of all public settings of this neighborhood, e.g. to notice when they change'''
+    return tuple(sorted(
+      (name, value) for name, value in vars(cls).items()
+      if not name.startswith("_") and isinstance(value, (bool, int, float, str))
+    ))
+
+  @classmethod
+  def evaluate_moves(cls, solution: BoxSolution, moves: list[Move], stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Takes a list of moves and evaluates them on the given solution, counting them in `stats`.
+    Only the moves that lead to a valid solution are kept.
+    '''
+    scored_moves = []
+    for move in moves:
+      score = cls.generate_heuristic(solution, move)
+      # Skip invalid moves
+      if not cls.check_candidate(score, stats):
+        continue
+      scored_moves.append(ScoredMove(move, score))
+    return scored_moves
 
   @classmethod
-  @abstractmethod
   def get_neighbors(cls, solution: BoxSolution) -> list[ScoredMove]:
     '''
     Calculates neighbors of a given start solution.
     '''
+    return cls.get_neighbors_with_stats(solution)[0]
 
   @classmethod
   @abstractmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a given start solution, counting every candidate that is enumerated for them in `stats`.
+    '''
+
+  @classmethod
+  def get_neighbor_solutions(cls, solution: BoxSolution) -> list[tuple[BoxSolution, AbstractHeuristic]]:
+    '''
+    Convenience adapter for callers that want every neighbor as a solution of its own instead of a move.
+    Each move is applied to a copy of the solution, so this costs a full copy per neighbor, but leaves the solution
+    alone even for moves that can't be undone exactly. Algorithms should work with the moves of `get_neighbors` instead.
+    '''
+    neighbors = []
+    for (move, score) in cls.get_neighbors(solution):
+      # Copying both together keeps the move pointing at the rects of the copied solution
+      neighbor, neighbor_move = deepcopy((solution, move))
+      if not neighbor_move.apply_to_solution(neighbor):
+        continue
+      neighbors.append((neighbor, score))
+    return neighbors
+
+  @classmethod
+  @abstractmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''
+    Estimates the number of candidate moves `get_neighbors` would have to evaluate, without generating any.
+    This is an upper bound, since generation may stop early or skip candidates.
+    '''
+
+  @classmethod
   def generate_heuristic(cls, solution: BoxSolution, move: Move = None) -> AbstractHeuristic:
     '''
     Calculates a heuristic score for a given solution after applying a move.
     If the move is None, the heuristic score of the solution is calculated.
+    By default this is the generic score of the box problem, neighborhoods with a score of their own override it.
     '''
+    move_successful = True
+
+    # Perform move
+    if move is not None:
+      move_successful = move.apply_to_solution(solution)
+
+    # If move was unsuccessful, it resulted in an invalid solution
+    if not move_successful:
+      return GenericHeuristic(None, None, None, None)
+
+    # If move is valid, construct a proper score,
+    heuristic = GenericHeuristic(
+      len(solution.unplaced_rects),
+      len(solution.boxes),
+      solution.compute_box_entropy(),
+      solution.compute_incident_edge_coordinates()
+    )
+
+    # Undo the move operation
+    if move is not None:
+      move.undo(solution)
+    return heuristic
@@ -1,6 +1,8 @@
 from __future__ import annotations
 import logging
 from dataclasses import dataclass
+from itertools import combinations
+from typing import Optional
 import random
 # from multiprocessing import Pool, cpu_count
 # from copy import deepcopy
@@ -8,9 +10,9 @@ import random
 
 from problem.box_problem.geometry import Box, Rectangle
 from problem.box_problem.box_heuristic import PermutationHeuristic
-from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
 from utils import flatten
-from .neighborhood import Neighborhood
+from .neighborhood import Neighborhood, NeighborhoodStats
 from ..move import ScoredMove, Move
 
 logger = logging.getLogger(__name__)
@@ -24,56 +26,158 @@ logger = logging.getLogger(__name__)
 
 #TODO: implement generate_heuristic for this neighborhood
 
+@dataclass
+class EncodedSolution:
+  '''Permutation encoding of a solution, together with the constraints of the solution it was encoded from'''
+  rects: list[Rectangle]
+  '''Order in which the rects are placed into boxes'''
+  side_length: int
+  '''Side length of the boxes to decode into'''
+  grid_unit: int = 1
+  '''Grid unit of the boxes to decode into'''
+  max_boxes: Optional[int] = None
+  '''Number of boxes that may be opened while decoding, `None` for as many as needed'''
+
+  def copy(self) -> EncodedSolution:
+    '''Copies the encoding along with its rects'''
+    return EncodedSolution([r.copy() for r in self.rects], self.side_length, self.grid_unit, self.max_boxes)
+
 class Permutation(Neighborhood):
   '''Implementation for a permutation-based neighborhood'''
 
   # n_proc = max(int(os.environ.get("OPTALGO_MAX_CPU", 0)), cpu_count())
 
+  cacheable = False
+  '''Moves hold on to the rect objects of the solution they were generated for'''
+
   @classmethod
   def initialize(cls, solution: BoxSolution) -> BoxSolution:
     '''Initializes the neighborhood by returning the initial solution'''
     encoded_sol = cls.encode_solution(solution)
-    encoded_sol.sort(key=lambda x: x.get_width() * x.get_height(), reverse=True)
-    decoded_sol = cls.decode_rect_list(encoded_sol, solution.side_length)
+    encoded_sol.rects.sort(key=lambda x: x.get_width() * x.get_height(), reverse=True)
+    decoded_sol = cls.decode_solution(encoded_sol)
     solution.boxes = { box.id: box for box in decoded_sol }
+    solution.unplaced_rects = cls.leftover_rects(encoded_sol)
     return solution
 
   @classmethod
-  def encode_solution(cls, solution: BoxSolution) -> list[Rectangle]:
+  def encode_solution(cls, solution: BoxSolution) -> EncodedSolution:
     '''
-    Turns the solution into a list of boxes
+    Turns the solution into a list of rects, remembering the side length, grid unit and box limit of the solution
     '''
-    # TODO: remove this function from the permutationMove class.
-    return flatten([b.rects.values() for b in solution.boxes.values()])
+    # Unplaced rects go last, so they get another chance whenever the ordering changes
+    rects = flatten([b.rects.values() for b in solution.boxes.values()]) + list(solution.unplaced_rects.values())
+    return EncodedSolution(rects, solution.side_length, solution.grid_unit, solution.max_boxes)
 
   @classmethod
-  def decode_rect_list(cls, rects: list[Rectangle], box_length: int) -> list[Box]:
+  def decode_solution(cls, encoded: EncodedSolution) -> list[Box]:
+    '''
+    Turns an encoded solution back into boxes under the constraints it was encoded with.
+    '''
+    return cls.decode_rect_list(encoded.rects, encoded.side_length, encoded.grid_unit, encoded.max_boxes)
+
+  @classmethod
+  def decode_rect_list(
+      cls,
+      rects: list[Rectangle],
+      box_length: int,
+      grid_unit: int = 1,
+      max_boxes: Optional[int] = None
+    ) -> list[Box]:
     '''
     Turns a list of rectangles into a valid solution to the box-rect problem.
+    Raises a ValueError if a rect does not even fit into an empty box of the given side length,
+    as it would get lost otherwise. Once `max_boxes` are open, rects that don't fit into the last one
+    are marked as unplaced instead, see `leftover_rects`.
     '''
 
     # Take rects one by one and put them into a new box..
     # # Once one boundary is crossed, start with a new box
-    boxes = [Box(0, box_length)]
+    boxes = [Box(0, box_length, grid_unit=grid_unit)]
     current_box = boxes[0]
     # Go through rects until all have been processed
     for rect in rects:
       # for the rect check if it fits somewhere in the current box
       succ = current_box.fit_rect_compress(rect)
       if not succ:
+        if max_boxes is not None and len(boxes) >= max_boxes:
+          rect.move_to(0, 0, box_id=UNPLACED_BOX_ID)
+          continue
         # If it does not fit, create a new box and put it there
-        current_box = Box(len(boxes), box_length)
-        current_box.fit_rect_compress(rect)
+        current_box = Box(len(boxes), box_length, grid_unit=grid_unit)
+        if not current_box.fit_rect_compress(rect):
+          raise ValueError(f"Rect {rect.id} does not fit into a box of side length {box_length}")
         boxes.append(current_box)
+    cls.check_decode(rects, boxes, box_length, max_boxes)
     return boxes
 
+  @staticmethod
+  def decode_violations(
+      rects: list[Rectangle],
+      boxes: list[Box],
+      box_length: int,
+      max_boxes: Optional[int] = None
+    ) -> list[str]:
+    '''
+    Lists every way in which decoded boxes break the contract of `decode_rect_list`:
+    each of the rects ends up exactly once, either in a box or unplaced, within the bounds of its box
+    and without overlapping another rect. Returns an empty list if the decoding is feasible.
+    '''
+    violations = []
+    expected = [r.id for r in rects if r.box_id != UNPLACED_BOX_ID]
+    placed = [r.id for b in boxes for r in b.rects.values()]
+    if sorted(placed) != sorted(expected):
+      missing = set(expected) - set(placed)
+      extra = set(placed) - set(expected)
+      duplicates = len(placed) - len(set(placed))
+      violations.append(f"Placed rects don't match: {len(missing)} missing, {len(extra)} unknown, {duplicates} duplicates")
+
+    for box in boxes:
+      for rect in box.rects.values():
+        if rect.box_id != box.id:
+          violations.append(f"Rect {rect.id} in box {box.id} belongs to box {rect.box_id}")
+        if rect.get_x() + rect.get_width() > box_length or rect.get_y() + rect.get_height() > box_length:
+          violations.append(f"Rect {rect.id} sticks out of box {box.id}")
+      for rect_a, rect_b in combinations(box.rects.values(), 2):
+        if rect_a.overlaps(rect_b):
+          violations.append(f"Rects {rect_a.id} and {rect_b.id} overlap in box {box.id}")
+    # Rects only stay unplaced if there was no box left to open for them
+    if any(r.box_id == UNPLACED_BOX_ID for r in rects) and \
+        (max_boxes is None or len(boxes) < max_boxes):
+      violations.append("Rects were left unplaced although another box could have been opened")
+    return violations
+
   @classmethod
-  def evaluate_moves(cls, solution: BoxSolution, moves: list[PermutationMove]) -> list[ScoredMove]:
+  def check_decode(cls, rects: list[Rectangle], boxes: list[Box], box_length: int, max_boxes: Optional[int] = None):
+    '''
+    Sanity check that a decoding is feasible, which the bottom-left placement guarantees for any ordering.
+    The permutation local search, the memetic algorithm and the swap moves all rely on it.
+    Only runs in debug mode, i.e. it is skipped with `python -O`.
+    '''
+    if not __debug__:
+      return
+    violations = cls.decode_violations(rects, boxes, box_length, max_boxes)
+    assert len(violations) == 0, f"Infeasible decoding: {'; '.join(violations)}"
+
+  @staticmethod
+  def leftover_rects(encoded: EncodedSolution) -> dict[int, Rectangle]:
+    '''Returns the rects the last decoding of the encoded solution found no box for, as an unplaced pool'''
+    return { r.id: r for r in encoded.rects if r.box_id == UNPLACED_BOX_ID }
+
+  @classmethod
+  def evaluate_moves(
+      cls,
+      solution: BoxSolution,
+      moves: list[PermutationMove],
+      stats: NeighborhoodStats
+    ) -> list[ScoredMove]:
     scored_moves = []
     for move in moves:
       heuristic_score = cls.generate_heuristic(solution, move)
       if move.is_fill:
         heuristic_score = cls.generate_heuristic(solution, move)
+      # Every ordering decodes into a feasible solution, so this only counts the candidate
+      cls.check_candidate(heuristic_score, stats)
       scored_moves.append(ScoredMove(move, heuristic_score))
     return scored_moves
 
@@ -82,7 +186,7 @@ class Permutation(Neighborhood):
     '''Generates a list of permutation moves to go from this solution to a neighboring one
         A Permutation is a swap of two rectangles or a flip of one rectangle.'''
     moves: list[PermutationMove] = []
-    encoded_rects = cls.encode_solution(solution)
+    encoded_rects = cls.encode_solution(solution).rects
 
     # it first tries to fill remaining space of the boxes with rectangles located in succeeding boxes.
     # It then tries to get good swaps of rectangles to improve the solution.
@@ -111,6 +215,9 @@ class Permutation(Neighborhood):
     # for each rectangle check the space below and to the right.
     # If there is space, search for a rectangle that fits most optimally there and swap them.
     for i, rect_a in enumerate(encoded_rects):
+      # Unplaced rects have no free area around them, but they can still be swapped forward as rect B
+      if rect_a.box_id == UNPLACED_BOX_ID:
+        continue
       #get the free area around A.
       box_a = solution.boxes[rect_a.box_id]
       available_space_x : int = rect_a.get_width()
@@ -162,7 +269,7 @@ class Permutation(Neighborhood):
     return moves
 
   @classmethod
-  def get_neighbors(cls, solution: BoxSolution) -> list[ScoredMove]:
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
     '''
     Encodes the solution into a long list of rects that get placed from top left-to bottom-right
     in each box. Then computes permutations of this list and turns them back to solutions.
@@ -188,11 +295,17 @@ class Permutation(Neighborhood):
 
     # # Else just do it in this thread
     # else:
-    scored_moves = cls.evaluate_moves(solution, moves)
+    scored_moves = cls.evaluate_moves(solution, moves, stats)
 
     logger.info("Explored %i neighbors", len(scored_moves))
     return scored_moves
 
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''A fill-up move per box or a swap for every pair of rects'''
+    n_rects = sum(len(b.rects) for b in solution.boxes.values())
+    return max(len(solution.boxes), n_rects * (n_rects - 1) // 2)
+
   @classmethod
   def generate_heuristic(cls, solution: BoxSolution, move: Move = None):
     if move is None:
@@ -217,37 +330,6 @@ class PermutationMove(Move):
   flip: bool
   is_fill: bool = False
 
-
-
-  @classmethod
-  def encode_solution(cls, solution: BoxSolution) -> list[Rectangle]:
-    '''
-    Turns the solution into a list of boxes
-    '''
-    return flatten([b.rects.values() for b in solution.boxes.values()])
-
-  @classmethod
-  def decode_rect_list(cls, rects: list[Rectangle], box_length: int) -> list[Box]:
-    '''
-    Turns a list of rectangles into a valid solution to the box-rect problem.
-    '''
-
-    # Take rects one by one and put them into a new box..
-    # # Once one boundary is crossed, start with a new box
-    boxes = [Box(0, box_length)]
-    current_box = boxes[0]
-    # Go through rects until all have been processed
-    for rect in rects:
-      # for the rect check if it fits somewhere in the current box
-      succ = current_box.fit_rect_compress(rect)
-      if not succ:
-        # If it does not fit, create a new box and put it there
-        current_box = Box(len(boxes), box_length)
-        current_box.fit_rect_compress(rect)
-        boxes.append(current_box)
-    return boxes
-
-
   @classmethod
   def apply_fillup_move(
     cls,
@@ -287,13 +369,13 @@ class PermutationMove(Move):
       if not succ:
         return False
       if random.randint(1, 20) == 1:
-        solution.boxes = {
-          box.id: box
-          for box in self.decode_rect_list(self.encode_solution(solution), solution.side_length)
-          }
+        encoded = Permutation.encode_solution(solution)
+        solution.boxes = { box.id: box for box in Permutation.decode_solution(encoded) }
+        solution.unplaced_rects = Permutation.leftover_rects(encoded)
       return True
 
-    encoded_rects = self.encode_solution(solution)
+    encoded = Permutation.encode_solution(solution)
+    encoded_rects = encoded.rects
 
     rect_a = encoded_rects[self.first_idx]
     rect_b = encoded_rects[self.second_idx]
@@ -318,7 +400,8 @@ class PermutationMove(Move):
       this_rect.highlighted ^= True
 
     # Decode and modify in-place
-    solution.boxes = { box.id: box for box in self.decode_rect_list(encoded_rects, solution.side_length) }
+    solution.boxes = { box.id: box for box in Permutation.decode_solution(encoded) }
+    solution.unplaced_rects = Permutation.leftover_rects(encoded)
     return True
 
   def undo(self, solution: BoxSolution):
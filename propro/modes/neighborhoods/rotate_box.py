@@ -0,0 +1,87 @@
+from __future__ import annotations
+import logging
+from dataclasses import dataclass, field
+
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.geometry import Rectangle
+
+from .neighborhood import Neighborhood, NeighborhoodStats
+from ..move import Move, ScoredMove
+
+logger = logging.getLogger(__name__)
+
+class RotateBox(Neighborhood):
+  '''
+  Neighborhood which rotates the contents of a whole box at once.
+  For every box, all of its rects are flipped and laid out again from scratch,
+  which can free up space that moving single rects would never find.
+  '''
+
+  @classmethod
+  def generate_neighbors(cls, solution: BoxSolution, stats: NeighborhoodStats) -> list[ScoredMove]:
+    '''
+    Calculates neighbors of a solution by rotating the contents of every box.
+    '''
+    logger.info("Calculating RotateBox neighborhood")
+
+    moves = [RotateBoxMove(box_id) for box_id in list(solution.boxes.keys())]
+    scored_moves = cls.evaluate_moves(solution, moves, stats)
+
+    logger.info("Explored %i neighbors", len(scored_moves))
+    return scored_moves
+
+  @classmethod
+  def estimate_size(cls, solution: BoxSolution) -> int:
+    '''One candidate per box'''
+    return len(solution.boxes)
+
+@dataclass
+class RotateBoxMove(Move):
+  '''
+  Defines a move as flipping every rect of one box and packing them into it again.
+  The rects are placed in order of decreasing area at the first spot they fit in, preferring the rotated orientation.
+  '''
+  box_id: int
+
+  placements: list[tuple[Rectangle, int, int, int, bool]] = field(default_factory=list)
+  '''Record of `(rect, old_x, old_y, old_width, old_flipped)` for undoing the move'''
+
+  def apply_to_solution(self, solution: BoxSolution) -> bool:
+    '''
+    Tries to apply this move to a given box solution.
+    Will return false and leave the solution untouched if not all rects could be placed again
+    or if they ended up just where they were.
+    '''
+    box = solution.boxes[self.box_id]
+    rects = sorted(box.rects.values(), key=lambda r: r.get_area(), reverse=True)
+    self.placements = [(r, r.get_x(), r.get_y(), r.get_width(), r.flipped) for r in rects]
+
+    for rect in rects:
+      box.remove_rect(rect.id)
+      rect.flip()
+    for rect in rects:
+      if not box.fit_rect_compress(rect):
+        self.undo(solution)
+        return False
+      rect.highlighted = True
+
+    # Nothing changed, e.g. for a single square
+    if all((r.get_x(), r.get_y(), r.get_width()) == (x, y, width) for (r, x, y, width, _) in self.placements):
+      self.undo(solution)
+      return False
+    return True
+
+  def undo(self, solution: BoxSolution):
+    '''Undoes whatever this move had done to the argument solution'''
+    box = solution.boxes[self.box_id]
+    # Take out all rects first, so they don't block each other's old spots
+    for (rect, _, _, _, _) in self.placements:
+      box.remove_rect(rect.id)
+    for (rect, old_x, old_y, _, old_flipped) in self.placements:
+      # Squares don't change their width when flipped, so go by the flag
+      if rect.flipped != old_flipped:
+        rect.flip()
+      rect.move_to(old_x, old_y)
+      rect.highlighted = False
+      box.add_rect(rect)
+    self.placements = []
@@ -1,7 +1,7 @@
 from itertools import product
 from typing import Optional
 
-from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
 from problem.box_problem.geometry import Rectangle, Box
 
 from .selections import SelectionSchema, SelectionMove
@@ -19,7 +19,7 @@ class ByAreaSelection(SelectionSchema):
   def __fits_rect(box: Box, box_length: int, rect: Rectangle) -> Optional[tuple[int, int]]:
     '''Checks if a given rect fits somewhere in a box and returns the origin coordinates if possible'''
     # Loop over all origins
-    for origin in product(range(box_length), range(box_length)):
+    for origin in product(range(0, box_length, box.grid_unit), range(0, box_length, box.grid_unit)):
       # Set x/y of rect to these coordinates
       rect.move_to(*origin)
       # See if rect's coordinates are in the set of box free coordinates
@@ -32,7 +32,7 @@ class ByAreaSelection(SelectionSchema):
   def find_placement(partial_solution: BoxSolution, rect: Rectangle) -> SelectionMove:
     '''
     Finds a place for a given rectangle into the partial solution.
-    If no fit was found, a new box is created to accomodate it.
+    If no fit was found, a new box is created to accomodate it, unless `max_boxes` are already open.
     '''
     for box in partial_solution.boxes.values():
       # Skip full boxes
@@ -43,8 +43,11 @@ class ByAreaSelection(SelectionSchema):
       if possible_fit is not None:
         rect.move_to(*possible_fit)
         return SelectionMove(rect.id, box.id)
+    # If no box had room and none is left to open, the rect stays out
+    if not partial_solution.can_open_box():
+      return SelectionMove(rect.id, UNPLACED_BOX_ID)
     # If no box had room, create a new one
-    new_box = Box(len(partial_solution.boxes), partial_solution.side_length)
+    new_box = partial_solution.new_box(len(partial_solution.boxes))
     partial_solution.boxes[new_box.id] = new_box
     rect.move_to(0, 0)
     return SelectionMove(rect.id, new_box.id)
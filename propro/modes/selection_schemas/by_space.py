@@ -2,7 +2,7 @@ from collections import defaultdict
 from collections.abc import Iterator
 from operator import itemgetter
 
-from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
 from problem.box_problem.geometry import Rectangle, Box
 
 from .selections import SelectionSchema, SelectionMove
@@ -22,10 +22,11 @@ class BySpaceSelection(SelectionSchema):
   def find_minimal_coordinates(box: Box) -> Iterator[tuple[int, int]]:
     '''Yields coordinates in a box to search from'''
     all_coords = box.get_free_coordinates()
-    # Group free coordinates by x values to create vertical lines
+    # Group free coordinates on the grid by x values to create vertical lines
     coords_by_x = defaultdict(list)
     for x, y in all_coords:
-      coords_by_x[x].append((x, y))
+      if box.is_on_grid(x, y):
+        coords_by_x[x].append((x, y))
 
     # From each of these groups, yield the one with minimal y coordinate since we pack down
     for coords in sorted(coords_by_x.values(), key=itemgetter(0)):
@@ -59,7 +60,7 @@ class BySpaceSelection(SelectionSchema):
   def select(cls, partial_solution: BoxSolution, unprocessed_rects: list[Rectangle]) -> SelectionMove:
     '''
      Will look for the first space in the solution that fits one of the provided rectangles.
-     If none do, creates a new box.
+     If none do, creates a new box or leaves a rect unplaced once `max_boxes` are open.
     '''
     # Step 1: Go over all minimal coordinates
     for box in partial_solution.boxes.values():
@@ -87,7 +88,10 @@ class BySpaceSelection(SelectionSchema):
         rect.move_to(*coordinate)
         return SelectionMove(rect.id, box.id)
     # If we get to here, no coordinate in any box returned a possible rect, so a new box must be created
-    new_box = Box(len(partial_solution.boxes), partial_solution.side_length)
+    # If there is none left, the remaining rects stay out, starting with the largest one
+    if not partial_solution.can_open_box():
+      return SelectionMove(max(unprocessed_rects, key=lambda r: r.get_area()).id, UNPLACED_BOX_ID)
+    new_box = partial_solution.new_box(len(partial_solution.boxes))
     partial_solution.boxes[new_box.id] = new_box
     # To fill this box, the easiest rect is the one with maximum area
     rect = max(unprocessed_rects, key=lambda r: r.get_area())
@@ -6,7 +6,7 @@ from __future__ import annotations
 from abc import abstractmethod
 from dataclasses import dataclass
 
-from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.box_solution import BoxSolution, UNPLACED_BOX_ID
 from problem.box_problem.geometry import Rectangle
 from ..mode import Mode
 from ..move import Move
@@ -32,14 +32,21 @@ class SelectionMove(Move):
   box_id: int
 
   def apply_to_solution(self, solution: BoxSolution, unprocessed_rects: dict[int, Rectangle] = None):
-    '''Adds the given rect to the solution'''
+    '''Adds the given rect to the solution, or to its unplaced pool if there is no box left for it'''
     rect = unprocessed_rects.pop(self.rect_id)
+    if self.box_id == UNPLACED_BOX_ID:
+      rect.move_to(0, 0, box_id=UNPLACED_BOX_ID)
+      solution.unplaced_rects[rect.id] = rect
+      return
     rect.highlighted = True
     box = solution.boxes.get(self.box_id)
     box.add_rect(rect)
 
   def undo(self, solution: BoxSolution, unprocessed_rects:dict[int, Rectangle] = None):
     # Just remove this rect from the solution again
+    if self.box_id == UNPLACED_BOX_ID:
+      unprocessed_rects[self.rect_id] = solution.unplaced_rects.pop(self.rect_id)
+      return
     box = solution.boxes.get(self.box_id)
     rect = box.remove_rect(self.rect_id)
     rect.highlighted = False
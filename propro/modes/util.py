@@ -2,7 +2,7 @@ from typing import Optional
 from itertools import chain
 
 from .mode import Mode
-from .neighborhoods import Neighborhood
+from .neighborhoods import Neighborhood, Permutation, Geometric, DissolveBox, CoarseToFine, RotateBox
 from .selection_schemas import SelectionSchema
 
 def get_available_modes(algo) -> list[Mode]:
@@ -16,6 +16,9 @@ def get_available_modes(algo) -> list[Mode]:
     case "LocalSearch": return Neighborhood.__subclasses__()
     case "SimulatedAnnealing": return Neighborhood.__subclasses__()
     case "GreedySearch": return SelectionSchema.__subclasses__()
+    case "PermutationLocalSearch": return [Permutation]
+    # The refining local search works on complete solutions without overlap
+    case "MemeticAlgorithm": return [Geometric, DissolveBox, CoarseToFine, RotateBox]
     case _: raise ValueError("Algorithm not supported")
 
 def get_mode_by_name(algo, name: str) -> Optional[Mode]:
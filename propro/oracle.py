@@ -0,0 +1,173 @@
+'''
+Exhaustive search for the optimal box count of tiny instances, to validate the scores of the algorithms against.
+'''
+
+import logging
+import random
+import sys
+from argparse import ArgumentParser
+from itertools import product
+from typing import Optional
+
+from rich.table import Table
+from rich.console import Console
+
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
+from algorithms import OptimizationAlgorithm
+from modes import get_available_modes
+from problem.box_problem.box_problem import BoxProblem
+from problem.box_problem.geometry import Box, Rectangle
+from stop_conditions import Stagnation
+
+MAX_ORACLE_RECTS = 6
+'''Largest number of rects the exhaustive search is allowed on, it grows exponentially with it'''
+
+def normal_positions(rects: list[Rectangle], box_length: int) -> list[int]:
+  '''
+  Returns all sums of the sides of a subset of the rects that are smaller than the box, in either orientation.
+  Every packing can be pushed left and down until each rect touches the border or another rect,
+  so it is enough to try these as coordinates of a rect's origin.
+  '''
+  sums = {0}
+  for rect in rects:
+    sums |= {s + side for s in sums for side in (rect.width, rect.height) if s + side < box_length}
+  return sorted(sums)
+
+def fits_into(rects: list[Rectangle], box_length: int, box_count: int) -> bool:
+  '''Checks by backtracking whether the rects can be packed into the given number of boxes'''
+  positions = normal_positions(rects, box_length)
+  boxes = [Box(n, box_length) for n in range(box_count)]
+  # Large rects first, they have the fewest options and fail the soonest
+  rects = sorted((r.copy() for r in rects), key=lambda r: r.get_area(), reverse=True)
+
+  def place(index: int, opened_boxes: int) -> bool:
+    if index == len(rects):
+      return True
+    rect = rects[index]
+    # Empty boxes are interchangeable, so only the first of them is tried
+    for box in boxes[:min(opened_boxes + 1, box_count)]:
+      orientations = [(rect.width, rect.height)]
+      if rect.width != rect.height:
+        orientations.append((rect.height, rect.width))
+      for (width, height) in orientations:
+        rect.orient(width, height)
+        for (x, y) in product(positions, positions):
+          if x + width > box_length or y + height > box_length:
+            continue
+          rect.move_to(x, y)
+          if not box.add_rect(rect):
+            continue
+          if place(index + 1, max(opened_boxes, box.id + 1)):
+            return True
+          box.remove_rect(rect.id)
+    return False
+
+  return place(0, 0)
+
+def optimal_box_count(rects: list[Rectangle], box_length: int) -> int:
+  '''
+  Computes the minimum number of boxes the rects can be packed into by trying every placement.
+  Only feasible for up to `MAX_ORACLE_RECTS` rects, raises a ValueError for more.
+  '''
+  if len(rects) > MAX_ORACLE_RECTS:
+    raise ValueError(f"The exhaustive search is limited to {MAX_ORACLE_RECTS} rects, got {len(rects)}")
+  box_count = BoxProblem.compute_lower_bound(rects, box_length)
+  while not fits_into(rects, box_length, box_count):
+    box_count += 1
+  return box_count
+
+def comparable_box_count(algorithm: OptimizationAlgorithm) -> Optional[int]:
+  '''
+  Returns the box count of the best solution of the algorithm, or `None` if it can't be compared to the optimum,
+  i.e. for partial, knapsack and overlapping solutions
+  '''
+  solution = algorithm.best_solution
+  if not algorithm.has_complete_solution() or solution.unplaced_rects or solution.currently_permissible_overlap > 0:
+    return None
+  return solution.get_heuristic_score().box_count
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--count",
+    type=int,
+    help="Number of instances to check",
+    default=10
+  )
+  add_problem_args(
+    parser,
+    rect_number=5,
+    rect_size="1-6",
+    box_length=6,
+    rect_number_help=f"Number of rects per instance, at most {MAX_ORACLE_RECTS}"
+  )
+  parser.add_argument(
+    '--tick-number',
+    type=int,
+    help="Number of ticks to run the algorithms for",
+    default=50
+  )
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="Seed of the first instance, the following ones count up from it",
+    default=0
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  configure_logging(args)
+
+  def generate_problem(n: int) -> BoxProblem:
+    return BoxProblem(
+      box_length=args.box_length,
+      n_rect=args.rect_number,
+      w_range=parse_range(args.rect_x),
+      h_range=parse_range(args.rect_y),
+      seed=args.seed + n
+    )
+
+  optima = []
+  for n in range(args.count):
+    problem = generate_problem(n)
+    rects = [r for b in problem.current_solution.boxes.values() for r in b.rects.values()]
+    optima.append(optimal_box_count(rects, args.box_length))
+    logging.info(f"Instance {args.seed + n} needs {optima[-1]} boxes")
+
+  # Per variation (Algo / Mode / Compared runs / Optimal runs / Runs below the optimum)
+  results = []
+  for Algorithm in OptimizationAlgorithm.__subclasses__():
+    for Mode in get_available_modes(Algorithm):
+      logging.info(f"Running {Algorithm.__name__} with {Mode.__name__}")
+      compared, optimal, below = 0, 0, 0
+      for n, optimum in enumerate(optima):
+        # Every run starts from the same random state, so the results can be reproduced
+        random.seed(args.seed + n)
+        algorithm = Algorithm(generate_problem(n), Mode)
+        stagnation = Stagnation()
+        for _ in range(args.tick_number):
+          algorithm.tick()
+          if stagnation.is_met(algorithm):
+            break
+
+        box_count = comparable_box_count(algorithm)
+        if box_count is None:
+          continue
+        compared += 1
+        optimal += box_count == optimum
+        below += box_count < optimum
+      results.append((Algorithm.__name__, Mode.__name__, compared, optimal, below))
+
+  # Print results
+  table = Table("Algorithm", "Mode", "Compared", "Optimal", "Below optimum")
+  for (algo, mode, compared, optimal, below) in results:
+    table.add_row(algo, mode, str(compared), str(optimal), str(below))
+
+  console = Console()
+  console.print(table)
+
+  # Scoring below the optimum means a miscounted score or an invalid solution
+  if any(below > 0 for (_, _, _, _, below) in results):
+    sys.exit("Some algorithms reported fewer boxes than the optimum")
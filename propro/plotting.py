@@ -0,0 +1,22 @@
+'''
+Rendering of the convergence of a run to an image file, independent of the gui.
+'''
+
+from matplotlib.figure import Figure
+
+def export_convergence_plot(box_counts: list[int], lower_bound: int, path: str):
+  '''
+  Plots the box count after every tick together with the lower bound of the problem
+  and saves it to the given path. The format is taken from the file extension, e.g. PNG.
+  '''
+  # Use a bare figure instead of pyplot, so this works without a display and next to the gui
+  figure = Figure(figsize=(8, 4.5))
+  axes = figure.subplots()
+  axes.plot(range(len(box_counts)), box_counts, label="Box count")
+  axes.axhline(lower_bound, color="gray", linestyle="--", label="Lower bound")
+  axes.set_xlabel("Tick")
+  axes.set_ylabel("Number of boxes")
+  axes.set_title("Convergence")
+  axes.grid(True, alpha=0.3)
+  axes.legend()
+  figure.savefig(path, dpi=150)
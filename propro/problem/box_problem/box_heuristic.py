@@ -13,6 +13,11 @@ class GenericHeuristic(AbstractHeuristic):
   Represents the score of a solution.
   `None` as a box count will indicate an invalid solution.
   '''
+  unplaced_count: int
+  '''
+  Number of rects that are left out of the packing, e.g. for lack of boxes under a box limit. Lower is better.
+  Comes first, so a solution with fewer boxes never wins by leaving rects out.
+  '''
   box_count: int
   '''Number of overall boxes in this solution. Lower is better.'''
   # Was planned as 'gain' but that can only work on a move, not on a solution
@@ -25,10 +30,10 @@ class GenericHeuristic(AbstractHeuristic):
     return self.box_count is not None
 
   def __iter__(self):
-    return iter((self.box_count, self.box_entropy, self.incident_edges))
+    return iter((self.unplaced_count, self.box_count, self.box_entropy, self.incident_edges))
 
   def __repr__(self):
-    return f"GenericHeuristic({self.box_count=}, {self.box_entropy=}, {self.incident_edges=})"
+    return f"GenericHeuristic({self.unplaced_count=}, {self.box_count=}, {self.box_entropy=}, {self.incident_edges=})"
 
   def __lt__(self, other: GenericHeuristic):
     # Handle invalid solutions first
@@ -40,6 +45,8 @@ class GenericHeuristic(AbstractHeuristic):
       return False
 
     # Compare in order of priority
+    if self.unplaced_count != other.unplaced_count:
+      return self.unplaced_count < other.unplaced_count
     if self.box_count != other.box_count:
       return self.box_count < other.box_count
     if self.box_entropy != other.box_entropy:
@@ -48,6 +55,7 @@ class GenericHeuristic(AbstractHeuristic):
 
   def __eq__(self, other: GenericHeuristic):
     return all([
+      self.unplaced_count == other.unplaced_count,
       self.box_count == other.box_count,
       self.box_entropy == other.box_entropy,
       self.incident_edges == other.incident_edges
@@ -148,6 +156,8 @@ class OverlapHeuristic(AbstractHeuristic):
   '''Number of rectangles who's overlap area is too large, lower is better.'''
   incident_edges: int
   '''Number of coordinates shared by two adjacent rects. Higher is better.'''
+  overlap_penalty: float = 0.0
+  '''Penalty for the area in which rects overlap, added to the box count. Lower is better.'''
 
   def is_valid(self):
     return self.illegally_overlapping_rects == 0
@@ -156,15 +166,18 @@ class OverlapHeuristic(AbstractHeuristic):
     return iter((self.box_count, self.illegally_overlapping_rects))
 
   def __repr__(self):
-    return f"OverlapHeuristic({self.box_count=}, {self.illegally_overlapping_rects=}, {self.incident_edges=})"
+    return (
+      f"OverlapHeuristic({self.box_count=}, {self.illegally_overlapping_rects=}, "
+      f"{self.incident_edges=}, {self.overlap_penalty=})"
+    )
 
   def __lt__(self, other: OverlapHeuristic):
     # If they differ in illegal overlap number its easy
     if self.illegally_overlapping_rects != other.illegally_overlapping_rects:
       return self.illegally_overlapping_rects < other.illegally_overlapping_rects
-    # Otherwise compare box counts
-    if self.box_count != other.box_count:
-      return self.box_count < other.box_count
+    # Otherwise compare box counts, made worse by the overlap they still need
+    if self.box_count + self.overlap_penalty != other.box_count + other.overlap_penalty:
+      return self.box_count + self.overlap_penalty < other.box_count + other.overlap_penalty
     # Finally, compare incident edges
     return self.incident_edges > other.incident_edges
 
@@ -174,8 +187,54 @@ class OverlapHeuristic(AbstractHeuristic):
     return all([
       self.box_count == value.box_count,
       self.illegally_overlapping_rects == value.illegally_overlapping_rects,
-      self.incident_edges == value.incident_edges
+      self.incident_edges == value.incident_edges,
+      self.overlap_penalty == value.overlap_penalty
     ])
 
   def __le__(self, other):
     return self < other or self == other
+
+
+@dataclass
+class KnapsackHeuristic(AbstractHeuristic):
+  '''
+  Specific heuristic implementation for the knapsack neighborhood.
+  `None` as a placed value will indicate an invalid solution.
+  '''
+
+  placed_value: int
+  '''Sum of the values of all packed rects, higher is better.'''
+  incident_edges: int
+  '''Number of coordinates shared by two adjacent rects. Higher is better.'''
+
+  def is_valid(self) -> bool:
+    return self.placed_value is not None
+
+  def __iter__(self):
+    # Negate the fields so that lower is better, like for the other heuristics
+    return iter((-self.placed_value, -self.incident_edges))
+
+  def __repr__(self):
+    return f"KnapsackHeuristic({self.placed_value=}, {self.incident_edges=})"
+
+  def __lt__(self, other: KnapsackHeuristic):
+    # Handle invalid solutions first
+    if other.placed_value is None:
+      return True
+    if self.placed_value is None:
+      return False
+
+    if self.placed_value != other.placed_value:
+      return self.placed_value > other.placed_value
+    return self.incident_edges > other.incident_edges
+
+  def __eq__(self, other: KnapsackHeuristic):
+    if not isinstance(other, KnapsackHeuristic):
+      return False
+    return all([
+      self.placed_value == other.placed_value,
+      self.incident_edges == other.incident_edges
+    ])
+
+  def __le__(self, other: KnapsackHeuristic):
+    return self < other or self == other
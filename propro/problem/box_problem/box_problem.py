@@ -1,27 +1,467 @@
-from random import choice
+from __future__ import annotations
+import random
+from collections import Counter
+from copy import deepcopy
+from itertools import product
+from math import ceil
+from typing import Optional, TYPE_CHECKING
 from ..problem import Problem
-from .box_solution import BoxSolution
+from .box_solution import BoxSolution, UNPLACED_BOX_ID
+from .box_heuristic import GenericHeuristic
 from .geometry import Box, Rectangle
 
+# Neighborhoods are built on top of the problem, so only import them for annotations
+if TYPE_CHECKING:
+  from modes import Neighborhood
+
 class BoxProblem(Problem):
   '''
   Implementation for the box-rectangle problem.
   Contains the initial starting parameters and a current solution
   '''
-  def __init__(self, box_length: int, n_rect: int, w_range: range, h_range: range):
+  seed: Optional[int]
+  '''Seed the rectangles of this instance were generated with, `None` if they were given'''
+  known_optimum: Optional[int]
+  '''Number of boxes of an optimal packing, if the instance was constructed around one'''
+  __lower_bound: int
+  __trivial_box_count: int
+
+  def __init__(
+      self,
+      box_length: int,
+      n_rect: int,
+      w_range: range,
+      h_range: range,
+      v_range: range = None,
+      seed: Optional[int] = None,
+      grid_unit: int = 1,
+      max_boxes: Optional[int] = None
+    ):
     '''
     Initializes the box problem with a trivial solution where each rectangle is in its own box.
+    If a value range is given, each rectangle gets a random value from it, otherwise its area is used.
+    The same seed will always generate the same instance. If no seed is given, a random one is picked.
+    The grid unit and the box limit are constraints of this instance, see `grid_unit` and `max_boxes`.
     '''
-    boxes = []
+    self.seed = seed if seed is not None else random.randrange(2**32)
+    self.known_optimum = None
+    rng = random.Random(self.seed)
+
+    rects = []
     for n in range(n_rect):
       # Get ourselves a nice rect tangle
-      width = choice(w_range)
-      height = choice(h_range)
-      rect = Rectangle(0, 0, width, height, n, n)
+      width = rng.choice(w_range)
+      height = rng.choice(h_range)
+      value = rng.choice(v_range) if v_range is not None else None
+      rects.append(Rectangle(0, 0, width, height, n, n, value))
+
+    self.__init_trivial_solution(box_length, rects, grid_unit, max_boxes)
+
+  def __init_trivial_solution(self, box_length: int, rects: list[Rectangle], grid_unit: int, max_boxes: Optional[int]):
+    '''
+    Puts every rect into its own box.
+    Rects that fit into no box at all are kept in the unplaced pool, so they don't get lost.
+    If the number of boxes is limited, the rects are packed first fit instead and those that are left over are unplaced.
+    '''
+    if grid_unit < 1:
+      raise ValueError("Grid unit must be at least 1")
+    if max_boxes is not None and max_boxes < 1:
+      raise ValueError("Maximum box count must be at least 1")
+    boxes = []
+    overflow = []
+    placeable = []
+    for rect in rects:
+      if max(rect.width, rect.height) > box_length:
+        rect.move_to(0, 0, box_id=UNPLACED_BOX_ID)
+        overflow.append(rect)
+      else:
+        placeable.append(rect)
 
-      # Now construct a new box and put just this one in it
-      boxes.append(Box(n, box_length, rect))
+    if max_boxes is None:
+      for rect in placeable:
+        rect.move_to(0, 0, box_id=len(boxes))
+        boxes.append(Box(len(boxes), box_length, rect, grid_unit=grid_unit))
+    else:
+      packing = BoxSolution(box_length, [], grid_unit=grid_unit, max_boxes=max_boxes)
+      packing.repair(placeable)
+      boxes = list(packing.boxes.values())
+      overflow += packing.unplaced_rects.values()
 
     # Finally, initialize the solution with list of boxes
-    self.current_solution = BoxSolution(box_length, boxes)
+    self.current_solution = BoxSolution(box_length, boxes, grid_unit=grid_unit, max_boxes=max_boxes)
+    self.current_solution.unplaced_rects = { r.id: r for r in overflow }
+    self.__lower_bound = self.compute_lower_bound(rects, box_length)
+    self.__trivial_box_count = len(boxes)
+
+  @classmethod
+  def from_rects(
+      cls,
+      box_length: int,
+      rects: list[Rectangle],
+      grid_unit: int = 1,
+      max_boxes: Optional[int] = None
+    ) -> BoxProblem:
+    '''Initializes the box problem for a given list of rects, with each rectangle in its own box'''
+    problem = cls.__new__(cls)
+    problem.seed = None
+    problem.known_optimum = None
+    problem.__init_trivial_solution(box_length, rects, grid_unit, max_boxes)
+    return problem
+
+  @classmethod
+  def with_known_optimum(
+      cls,
+      box_length: int,
+      num_boxes: int,
+      max_rect_length: Optional[int] = None,
+      seed: Optional[int] = None,
+      fill_ratio: float = 1.0
+    ) -> BoxProblem:
+    '''
+    Generates an instance whose optimum is known by construction.
+    Each of the `num_boxes` boxes is tiled completely by cutting it with random guillotine cuts
+    until no side is longer than `max_rect_length` (half the box by default). The pieces are shuffled
+    and put into their own box each, like any other instance. Since they fill exactly `num_boxes` boxes,
+    that is the optimum, which is stored as `known_optimum`.
+    With a `fill_ratio` below 1, random pieces are left out until they only cover that fraction of the boxes.
+    The optimum is then only known as long as the rest can't fit into fewer boxes by area.
+    '''
+    if max_rect_length is None:
+      max_rect_length = max(1, box_length // 2)
+    seed = seed if seed is not None else random.randrange(2**32)
+    rng = random.Random(seed)
+
+    sizes = []
+    for _ in range(num_boxes):
+      # Pieces that still need cutting
+      pieces = [(box_length, box_length)]
+      while len(pieces) > 0:
+        width, height = pieces.pop()
+        if width <= max_rect_length and height <= max_rect_length:
+          sizes.append((width, height))
+          continue
+        # Cut through the longer side, so pieces don't turn into long stripes
+        if width >= height:
+          cut = rng.randint(1, width - 1)
+          pieces += [(cut, height), (width - cut, height)]
+        else:
+          cut = rng.randint(1, height - 1)
+          pieces += [(width, cut), (width, height - cut)]
+    rng.shuffle(sizes)
+
+    # Keep pieces as long as they fit into the area budget
+    budget = fill_ratio * num_boxes * box_length**2
+    kept = []
+    for (width, height) in sizes:
+      if width * height <= budget:
+        kept.append((width, height))
+        budget -= width * height
+
+    problem = cls.from_rects(box_length, [Rectangle(0, 0, w, h, n) for n, (w, h) in enumerate(kept)])
+    problem.seed = seed
+    # The tiling still proves that num_boxes are enough, but fewer may be as well
+    problem.known_optimum = num_boxes if problem.get_lower_bound() == num_boxes else None
+    return problem
+
+  @classmethod
+  def from_dict(cls, data: dict) -> BoxProblem:
+    '''
+    Constructs an instance from a dict as produced by `to_dict`.
+    Rects without an id are numbered by their position, raises a ValueError if that makes two ids the same.
+    '''
+    rects = [
+      Rectangle(
+        0, 0, r["width"], r["height"], r.get("id", n),
+        value=r.get("value"),
+        splittable=r.get("splittable", False),
+        parent_id=r.get("parent_id")
+      )
+      for n, r in enumerate(data["rects"])
+    ]
+    if any(r.width <= 0 or r.height <= 0 for r in rects):
+      raise ValueError("Rectangles must have a positive width and height")
+    duplicates = sorted(i for i, count in Counter(r.id for r in rects).items() if count > 1)
+    if len(duplicates) > 0:
+      raise ValueError(f"Rect ids {', '.join(str(i) for i in duplicates)} are used more than once")
+    problem = cls.from_rects(
+      int(data["box_length"]),
+      rects,
+      grid_unit=int(data.get("grid_unit", 1)),
+      max_boxes=data.get("max_boxes")
+    )
+    problem.known_optimum = data.get("known_optimum")
+    return problem
+
+  @classmethod
+  def parse_2dpacklib(cls, text: str) -> BoxProblem:
+    '''
+    Constructs an instance from a bin packing instance in the format of 2DPackLib:
+    a line with the number of item types, a line with the width and height of the bins,
+    then one line per item type with its id, width, height and optionally the quantity and further columns,
+    which are ignored. An item type with quantity `q` becomes `q` rects with ids of their own.
+    Only square bins are supported, raises a ValueError naming the line of anything else that doesn't parse.
+    '''
+    lines = [(n, line.split()) for n, line in enumerate(text.splitlines(), start=1) if line.strip()]
+
+    def numbers(n: int, tokens: list[str], count: int, what: str) -> list[int]:
+      if len(tokens) < count:
+        raise ValueError(f"Line {n}: expected {what}, got '{' '.join(tokens)}'")
+      try:
+        return [int(t) for t in tokens[:count]]
+      except ValueError:
+        raise ValueError(f"Line {n}: expected {what} as whole numbers, got '{' '.join(tokens)}'") from None
+
+    if len(lines) < 2:
+      raise ValueError("Expected a line with the number of items and one with the bin dimensions")
+    (item_count,) = numbers(*lines[0], 1, "the number of items")
+    (width, height) = numbers(*lines[1], 2, "the bin width and height")
+    if width != height:
+      raise ValueError(f"Line {lines[1][0]}: only square bins are supported, got {width}x{height}")
+    if len(lines) - 2 != item_count:
+      raise ValueError(f"Expected {item_count} items, found {len(lines) - 2}")
+
+    rects = []
+    for (n, tokens) in lines[2:]:
+      (_, w, h) = numbers(n, tokens, 3, "an item id, width and height")
+      quantity = numbers(n, tokens, 4, "a quantity")[3] if len(tokens) > 3 else 1
+      if w <= 0 or h <= 0 or quantity < 0:
+        raise ValueError(f"Line {n}: items must have a positive width and height and a quantity of at least 0")
+      rects += [Rectangle(0, 0, w, h, len(rects) + i) for i in range(quantity)]
+    return cls.from_rects(width, rects)
+
+  def to_dict(self) -> dict:
+    '''Serializes the rects of this instance into a dict of plain values, e.g. for writing it as JSON'''
+    rects = sorted(
+      [r for b in self.current_solution.boxes.values() for r in b.rects.values()]
+        + list(self.current_solution.unplaced_rects.values()),
+      key=lambda r: r.id
+    )
+    def rect_to_dict(n: int, rect: Rectangle) -> dict:
+      r = { "width": rect.width, "height": rect.height, "value": rect.value }
+      # Leave out the defaults, so plain instances stay as they are.
+      # Ids only differ from the position once rects were split, they have to be kept for the parent ids to stay valid
+      if rect.id != n:
+        r["id"] = rect.id
+      if rect.splittable:
+        r["splittable"] = True
+      if rect.parent_id is not None:
+        r["parent_id"] = rect.parent_id
+      return r
+
+    data = {
+      "box_length": self.current_solution.side_length,
+      "rects": [rect_to_dict(n, r) for n, r in enumerate(rects)]
+    }
+    if self.known_optimum is not None:
+      data["known_optimum"] = self.known_optimum
+    if self.grid_unit != 1:
+      data["grid_unit"] = self.grid_unit
+    if self.max_boxes is not None:
+      data["max_boxes"] = self.max_boxes
+    return data
+
+  @property
+  def grid_unit(self) -> int:
+    '''
+    Rects may only be placed at coordinates that are multiples of this, e.g. for packing pallets.
+    Unlike a coarse search step, this is a feasibility constraint: moves off the grid are invalid.
+    It is given when the instance is constructed and kept by its solution, which hands it to every box.
+    '''
+    return self.current_solution.grid_unit
+
+  @property
+  def max_boxes(self) -> Optional[int]:
+    '''
+    Number of boxes that are available, `None` for as many as needed.
+    With a limit, minimizing the box count turns into finding a packing that fits at all:
+    no move may open a box beyond it and rects that don't fit are left unplaced, which the score counts first.
+    Like the grid unit, it is given when the instance is constructed and kept by its solution.
+    '''
+    return self.current_solution.max_boxes
+
+  @staticmethod
+  def compute_lower_bound(rects: list[Rectangle], box_length: int) -> int:
+    '''
+    Computes a lower bound for the number of boxes needed to pack the rects.
+    No packing can beat the total area divided by the box area, and rects which are longer
+    than half the box in both dimensions can't share a box with each other.
+    '''
+    if len(rects) == 0:
+      return 0
+    area_bound = ceil(sum(r.get_area() for r in rects) / box_length**2)
+    large_rects = sum(1 for r in rects if 2 * r.get_width() > box_length and 2 * r.get_height() > box_length)
+    return max(area_bound, large_rects)
+
+  def infeasible_rectangles(self) -> list[Rectangle]:
+    '''
+    Returns all rects which are larger than the box in both orientations.
+    Since the box is a square, these are the rects with one side longer than the box.
+    '''
+    solution = self.current_solution
+    rects = [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values())
+    return sorted(
+      [r for r in rects if max(r.width, r.height) > solution.side_length],
+      key=lambda r: r.id
+    )
+
+  def check_placeable(self):
+    '''
+    Raises a ValueError if any rect fits into no box at all.
+    Placement routines call this up front, since they would otherwise open a new box for it
+    and still not be able to place it.
+    '''
+    infeasible = self.infeasible_rectangles()
+    if len(infeasible) > 0:
+      raise ValueError(
+        f"Rects {', '.join(str(r.id) for r in infeasible)} don't fit into a box "
+        f"of side length {self.current_solution.side_length} in any orientation"
+      )
+
+  def split_oversized(self, max_dim: int) -> list[Rectangle]:
+    '''
+    Cuts every splittable rect with a side longer than `max_dim` into a grid of pieces no longer than that.
+    Pieces are `max_dim` long except for the last one along each side, get fresh ids and remember the id
+    of the rect they were cut from as `parent_id`, so they can be put back together. The value of a rect
+    is divided among its pieces by area. Since this changes the set of rects, the problem starts over with
+    the trivial solution. Returns the new pieces.
+    '''
+    if max_dim < 1:
+      raise ValueError("Pieces must be at least 1 long")
+    solution = self.current_solution
+    rects = sorted(
+      [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values()),
+      key=lambda r: r.id
+    )
+    next_id = max((r.id for r in rects), default=-1) + 1
+
+    kept = []
+    pieces = []
+    for rect in rects:
+      if not rect.splittable or max(rect.width, rect.height) <= max_dim:
+        kept.append(rect)
+        continue
+      # Sides of the pieces along the width and height
+      widths = [min(max_dim, rect.width - x) for x in range(0, rect.width, max_dim)]
+      heights = [min(max_dim, rect.height - y) for y in range(0, rect.height, max_dim)]
+      remaining_value = rect.value
+      for n, (width, height) in enumerate(product(widths, heights)):
+        # The last piece gets the rounding remainder, so the value adds up again
+        is_last = n == len(widths) * len(heights) - 1
+        value = remaining_value if is_last else rect.value * width * height // rect.get_area()
+        remaining_value -= value
+        pieces.append(Rectangle(0, 0, width, height, next_id, value=value, splittable=True, parent_id=rect.id))
+        next_id += 1
+
+    if len(pieces) == 0:
+      return []
+    for rect in kept:
+      rect.move_to(0, 0)
+    self.__init_trivial_solution(solution.side_length, kept + pieces, solution.grid_unit, solution.max_boxes)
+    # Cutting may allow packings into fewer boxes than the one the instance was built around
+    self.known_optimum = None
+    return pieces
+
+  def resize_boxes(self, side_length: int) -> int:
+    '''
+    Changes the side length of the boxes, keeping the current solution as far as possible.
+    Growing the boxes keeps every rect where it is, shrinking them relocates the rects that stick out
+    to the first spot they fit in, opening new boxes if needed and allowed by `max_boxes`.
+    Returns the number of boxes that were opened.
+    Raises a ValueError and leaves everything as it was if a rect wouldn't fit into a box of the new size.
+    '''
+    solution = self.current_solution
+    rects = [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values())
+    too_large = sorted(r.id for r in rects if max(r.width, r.height) > side_length)
+    if len(too_large) > 0:
+      raise ValueError(
+        f"Rects {', '.join(str(i) for i in too_large)} don't fit into a box of side length {side_length} in any orientation"
+      )
+
+    opened = solution.repair(solution.resize_boxes(side_length))
+    self.__lower_bound = self.compute_lower_bound(rects, side_length)
+    # The instance was built around boxes of the old size
+    self.known_optimum = None
+    return opened
+
+  def shuffle_into_random_feasible(self, seed: int, move_count: Optional[int] = None) -> int:
+    '''
+    Moves random rects of the current solution to random spots where they fit, so restarts don't all begin
+    at the same solution. The moves only depend on the seed, so the same seed on the same solution
+    leads to the same result. Rects only move within the boxes that are open already and emptied boxes are dropped,
+    so the box count never goes up. Every rect gets one attempt by default.
+    Returns the number of rects that were moved.
+    '''
+    rng = random.Random(seed)
+    solution = self.current_solution
+    if move_count is None:
+      move_count = sum(len(b.rects) for b in solution.boxes.values())
+
+    moved = 0
+    for _ in range(move_count):
+      if len(solution.boxes) == 0:
+        break
+      source = rng.choice(list(solution.boxes.values()))
+      target = rng.choice(list(solution.boxes.values()))
+      rect = source.remove_rect(rng.choice(list(source.rects.keys())))
+      (old_x, old_y, old_width, old_height) = (rect.get_x(), rect.get_y(), rect.width, rect.height)
+
+      origins = [c for c in sorted(target.get_free_coordinates()) if target.is_on_grid(*c)]
+      rng.shuffle(origins)
+      orientations = [(old_width, old_height)] if old_width == old_height else [(old_width, old_height), (old_height, old_width)]
+      rng.shuffle(orientations)
+      # The box checks that every coordinate of the rect is free, which also keeps it within bounds
+      placed = False
+      for ((x, y), (width, height)) in product(origins, orientations):
+        rect.orient(width, height)
+        rect.move_to(x, y)
+        if target.add_rect(rect):
+          placed = True
+          break
+      if not placed:
+        rect.orient(old_width, old_height)
+        rect.move_to(old_x, old_y)
+        source.add_rect(rect)
+        continue
+
+      moved += 1
+      if len(source.rects) == 0:
+        solution.boxes.pop(source.id)
+    return moved
+
+  def tightness(self) -> float:
+    '''
+    Measures how tightly the rects have to be packed, as the fraction of the area of
+    the lower bound's boxes that the rects cover. 1 means that only a perfect tiling reaches the bound.
+    '''
+    if self.__lower_bound == 0:
+      return 0.0
+    solution = self.current_solution
+    rects = [r for b in solution.boxes.values() for r in b.rects.values()] + list(solution.unplaced_rects.values())
+    return sum(r.get_area() for r in rects) / (self.__lower_bound * solution.side_length**2)
+
+  def reachable_scores(self, neighborhood: type[Neighborhood]) -> list[tuple[GenericHeuristic, int]]:
+    '''
+    Collects the distinct scores of all solutions one move of the neighborhood away from the current one,
+    each with the number of neighbors that reach it, best first. Neighborhoods score their moves in different ways,
+    so every neighbor is scored as a solution of its own to make neighborhoods comparable.
+    The neighborhood first initializes a copy of the current solution like at the start of a run,
+    since some of them only work on solutions they prepared. The neighbors are copies as well, see
+    `Neighborhood.get_neighbor_solutions`, which is slow but leaves the current solution alone.
+    '''
+    start = neighborhood.initialize(deepcopy(self.current_solution))
+    scores: dict[tuple, tuple[GenericHeuristic, int]] = {}
+    for (neighbor, _) in neighborhood.get_neighbor_solutions(start):
+      score = neighbor.get_heuristic_score()
+      if score.is_valid():
+        (_, count) = scores.get(tuple(score), (score, 0))
+        scores[tuple(score)] = (score, count + 1)
+    return sorted(scores.values(), key=lambda s: s[0])
+
+  def get_lower_bound(self) -> int:
+    '''Returns the lower bound for the number of boxes of this instance'''
+    return self.__lower_bound
+
+  def get_trivial_box_count(self) -> int:
+    '''Returns the number of boxes of the trivial solution this instance started with, as a baseline for the search'''
+    return self.__trivial_box_count
     
\ No newline at end of file
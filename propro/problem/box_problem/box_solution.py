@@ -4,6 +4,9 @@ from __future__ import annotations
 from itertools import combinations
 from math import log2
 from collections import deque
+from dataclasses import dataclass
+from statistics import fmean, pstdev
+from typing import Optional
 
 
 from ..solution import Solution
@@ -11,6 +14,23 @@ from .geometry import Box, Rectangle
 from ..heuristic import AbstractHeuristic
 from .box_heuristic import GenericHeuristic
 
+UNPLACED_BOX_ID = -1
+'''Virtual box id of rectangles that are left out of the packing'''
+
+@dataclass
+class FillStats:
+  '''
+  Aggregate of the fill ratios of all non-empty boxes of a solution.
+  A high deviation means the packing is lopsided, i.e. some boxes are nearly empty and could likely be consolidated.
+  '''
+  min: float
+  max: float
+  mean: float
+  stddev: float
+  '''Population standard deviation of the fill ratios'''
+
+  def __str__(self):
+    return f"min {self.min:.0%} / max {self.max:.0%} / mean {self.mean:.0%} / stddev {self.stddev:.0%}"
 
 class BoxSolution(Solution):
   '''
@@ -21,19 +41,29 @@ class BoxSolution(Solution):
   '''Lookup from box id to object'''
   side_length: int
   '''Side length of all boxes'''
+  grid_unit: int
+  '''Unit that the coordinates of all rects have to be multiples of, handed to every box of this solution'''
+  max_boxes: Optional[int]
+  '''Number of boxes that are available, `None` if the solution may open as many as it likes'''
   currently_permissible_overlap: float
   '''Fraction of overlap that is allowed between two rectangles'''
 
+  unplaced_rects: dict[int, Rectangle]
+  '''Rectangles which are currently not packed into any box, by the knapsack variant or for lack of boxes under `max_boxes`.'''
+
   last_moved_rect_ids: deque[int]
   '''Queue of last moved rect ids'''
 
-  def __init__(self, side_length: int, box_list: list[Box]):
+  def __init__(self, side_length: int, box_list: list[Box], grid_unit: int = 1, max_boxes: Optional[int] = None):
     '''
     Initialize the solution with a list of box objects
     '''
     self.currently_permissible_overlap = 0.0
     self.side_length = side_length
+    self.grid_unit = grid_unit
+    self.max_boxes = max_boxes
     self.boxes = {}
+    self.unplaced_rects = {}
     # Initialize queue with max length = rect count / 4
     self.last_moved_rect_ids = deque(maxlen=int(sum(len(b.rects) for b in box_list) / 4))
     for box in box_list:
@@ -45,19 +75,175 @@ class BoxSolution(Solution):
     s += '\n'.join([str(box) for box in self.boxes.values()])
     return s
 
+  def new_box(self, box_id: int, *rects: Rectangle, calc_coords: bool = True) -> Box:
+    '''Creates a box that fits into this solution, i.e. with its side length and grid unit'''
+    return Box(box_id, self.side_length, *rects, calc_coords=calc_coords, grid_unit=self.grid_unit)
+
+  def rectangles_in_box(self, box_id: int) -> Optional[list[Rectangle]]:
+    '''Returns the rects packed into the box with the given id, `None` if there is no such box'''
+    box = self.boxes.get(box_id)
+    if box is None:
+      return None
+    return list(box.rects.values())
+
+  def find_rectangle(self, rect_id: int) -> Optional[tuple[int, Rectangle]]:
+    '''
+    Looks up a rect by its id and returns it along with the id of the box it is in.
+    Unplaced rects are returned with `UNPLACED_BOX_ID`, `None` means there is no such rect.
+    '''
+    for box in self.boxes.values():
+      if rect_id in box.rects:
+        return (box.id, box.rects[rect_id])
+    if rect_id in self.unplaced_rects:
+      return (UNPLACED_BOX_ID, self.unplaced_rects[rect_id])
+    return None
+
+  def is_guillotine_cuttable(self) -> bool:
+    '''Checks whether every box of this solution can be cut apart with edge-to-edge cuts only'''
+    return all(box.is_guillotine_cuttable() for box in self.boxes.values())
+
+  def canonicalize(self):
+    '''
+    Brings the solution into a canonical form, so two solutions with the same packing
+    are represented the same, no matter which moves led to them.
+    The rects in each box are sorted by `(x, y, id)`, the boxes are sorted by their content
+    and renumbered from 0. Unplaced rects are sorted by id.
+    '''
+    def rect_key(rect: Rectangle) -> tuple:
+      return (rect.get_x(), rect.get_y(), rect.id)
+
+    for box in self.boxes.values():
+      box.rects = { rect.id: rect for rect in sorted(box.rects.values(), key=rect_key) }
+
+    # A box's content is the list of its rects with their dimensions
+    boxes = sorted(
+      self.boxes.values(),
+      key=lambda b: [(*rect_key(r), r.get_width(), r.get_height()) for r in b.rects.values()]
+    )
+    for box_id, box in enumerate(boxes):
+      box.set_box_id(box_id)
+      box.needs_redraw = True
+    self.boxes = { box.id: box for box in boxes }
+
+    self.unplaced_rects = dict(sorted(self.unplaced_rects.items()))
+
+  def get_order(self) -> tuple:
+    '''
+    Returns the order of the boxes, of the rects within them, of the unplaced rects and of the last moved rects.
+    Undoing a move puts its rect back at the end of its box, while the neighborhoods enumerate the rects in order.
+    '''
+    return (
+      { box_id: list(box.rects.keys()) for box_id, box in self.boxes.items() },
+      list(self.unplaced_rects.keys()),
+      list(self.last_moved_rect_ids)
+    )
+
+  def restore_order(self, order: tuple):
+    (box_order, unplaced_order, last_moved_rect_ids) = order
+    self.boxes = { box_id: self.boxes[box_id] for box_id in box_order }
+    for box_id, rect_ids in box_order.items():
+      box = self.boxes[box_id]
+      box.rects = { rect_id: box.rects[rect_id] for rect_id in rect_ids }
+    self.unplaced_rects = { rect_id: self.unplaced_rects[rect_id] for rect_id in unplaced_order }
+    self.last_moved_rect_ids.clear()
+    self.last_moved_rect_ids.extend(last_moved_rect_ids)
+
+  def solution_hash(self) -> int:
+    '''
+    Hashes the placement of every rect, including the box ids, the allowed overlap and the constraints of the solution.
+    Two solutions with the same hash can be assumed to be identical. The hash does not depend on
+    the order of the rects, but on the box ids, so call `canonicalize` first to ignore those as well.
+    '''
+    placements = tuple(sorted(
+      (box.id, rect.id, rect.get_x(), rect.get_y(), rect.get_width())
+      for box in self.boxes.values()
+      for rect in box.rects.values()
+    ))
+    return hash((
+      placements,
+      tuple(sorted(self.unplaced_rects.keys())),
+      self.currently_permissible_overlap,
+      self.grid_unit,
+      self.max_boxes
+    ))
+
+  def to_dict(self) -> dict:
+    '''Serializes this solution into a dict of plain values, e.g. for writing it as JSON'''
+    def rect_to_dict(rect: Rectangle) -> dict:
+      return {
+        "id": rect.id,
+        "x": rect.get_x(),
+        "y": rect.get_y(),
+        "width": rect.get_width(),
+        "height": rect.get_height(),
+        "value": rect.value,
+        "flipped": rect.flipped,
+        "parent_id": rect.parent_id
+      }
+
+    def box_to_dict(box: Box) -> dict:
+      b = { "id": box.id, "rects": [rect_to_dict(r) for r in box.rects.values()] }
+      # Boxes of the overlap neighborhood don't track coordinates, so their rects may overlap
+      if not box.calc_coords:
+        b["calc_coords"] = False
+      return b
+
+    data = {
+      "side_length": self.side_length,
+      "boxes": [box_to_dict(box) for box in self.boxes.values()],
+      "unplaced_rects": [rect_to_dict(r) for r in self.unplaced_rects.values()]
+    }
+    if self.grid_unit != 1:
+      data["grid_unit"] = self.grid_unit
+    if self.max_boxes is not None:
+      data["max_boxes"] = self.max_boxes
+    return data
+
+  @classmethod
+  def from_dict(cls, data: dict) -> BoxSolution:
+    '''
+    Constructs a solution from a dict as produced by `to_dict`.
+    Raises a ValueError if a box refuses one of its rects, e.g. because they overlap, instead of losing it.
+    '''
+    def rect_from_dict(r: dict, box_id: int) -> Rectangle:
+      return Rectangle(
+        r["x"], r["y"], r["width"], r["height"], r["id"], box_id, r.get("value"), r.get("flipped", False),
+        parent_id=r.get("parent_id")
+      )
+
+    side_length = data["side_length"]
+    grid_unit = int(data.get("grid_unit", 1))
+    boxes = []
+    for b in data["boxes"]:
+      rects = [rect_from_dict(r, b["id"]) for r in b["rects"]]
+      box = Box(b["id"], side_length, *rects, calc_coords=b.get("calc_coords", True), grid_unit=grid_unit)
+      refused = sorted(r.id for r in rects if r.id not in box.rects)
+      if len(refused) > 0:
+        raise ValueError(f"Rects {', '.join(str(i) for i in refused)} don't fit into box {b['id']} where they were saved")
+      boxes.append(box)
+    solution = cls(side_length, boxes, grid_unit=grid_unit, max_boxes=data.get("max_boxes"))
+    for r in data.get("unplaced_rects", []):
+      solution.unplaced_rects[r["id"]] = rect_from_dict(r, UNPLACED_BOX_ID)
+    return solution
+
   def calculate_heuristic_score(self, move) -> AbstractHeuristic:
     '''
     Calculates the heuristic score of the solution after a given move.
     '''
     # Perform move
-    move_sucessful = move.apply_to_solution(self)
+    move_successful = move.apply_to_solution(self)
 
     # If move was unsuccessful, it resulted in an invalid solution
-    if not move_sucessful:
-      return GenericHeuristic(None, None, None)
+    if not move_successful:
+      return GenericHeuristic(None, None, None, None)
 
     # If move is valid, construct a proper score,
-    score = GenericHeuristic(len(self.boxes), self.compute_box_entropy(), self.compute_incident_edge_coordinates())
+    score = GenericHeuristic(
+      len(self.unplaced_rects),
+      len(self.boxes),
+      self.compute_box_entropy(),
+      self.compute_incident_edge_coordinates()
+    )
 
     # Undo the move operation
     move.undo(self)
@@ -80,6 +266,10 @@ class BoxSolution(Solution):
     entropy = -sum(p * log2(p) if p > 0 else 0.0 for p in probabilities)
     return entropy
 
+  def compute_placed_value(self) -> int:
+    '''Sums up the values of all rects that are placed in a box'''
+    return sum(rect.value for box in self.boxes.values() for rect in box.rects.values())
+
   def count_illegal_overlaps(self) -> int:
     '''Counts the number of rectangles that overlap illegally.'''
     n = 0
@@ -89,18 +279,45 @@ class BoxSolution(Solution):
           n += 2
     return n
 
+  def fill_stats(self) -> FillStats:
+    '''Computes the fill statistics over all non-empty boxes, all zero if there are none'''
+    ratios = [box.get_fill_ratio() for box in self.boxes.values() if len(box.rects) > 0]
+    if len(ratios) == 0:
+      return FillStats(0.0, 0.0, 0.0, 0.0)
+    return FillStats(min(ratios), max(ratios), fmean(ratios), pstdev(ratios))
+
+  def compute_overlap_area(self) -> int:
+    '''Sums up the area that two rects of the same box cover at once, over all pairs of rects'''
+    return sum(
+      rect_a.overlap_area(rect_b)
+      for box in self.boxes.values()
+      for rect_a, rect_b in combinations(box.rects.values(), 2)
+    )
+
   # TODO: don't re-calculate this every time
   def get_heuristic_score(self) -> GenericHeuristic:
     if not self.is_valid():
-      return GenericHeuristic(None, None, None)
+      return GenericHeuristic(None, None, None, None)
 
     # Calculate all aspects of a score
+    unplaced_count = len(self.unplaced_rects)
     box_counts = len(self.boxes)
     box_entropy = self.compute_box_entropy()
     incident_edges = self.compute_incident_edge_coordinates()
-    return GenericHeuristic(box_counts, box_entropy, incident_edges)
+    return GenericHeuristic(unplaced_count, box_counts, box_entropy, incident_edges)
+
+  def can_open_box(self) -> bool:
+    '''Checks whether another box may be opened without going over `max_boxes`'''
+    return self.max_boxes is None or len(self.boxes) < self.max_boxes
+
+  def exceeds_max_boxes(self) -> bool:
+    '''Checks whether the solution uses more boxes than are available'''
+    return self.max_boxes is not None and len(self.boxes) > self.max_boxes
 
   def is_valid(self):
+    if self.exceeds_max_boxes():
+      return False
+
     # Go over all rects in all boxes
     for box in self.boxes.values():
       # Easy case: Rect is out-of-bounds
@@ -114,6 +331,52 @@ class BoxSolution(Solution):
           return False
     return True
 
+  def resize_boxes(self, side_length: int) -> list[Rectangle]:
+    '''
+    Changes the side length of all boxes. Rects keep their place as long as they still fit there,
+    the others are taken out and returned, e.g. to `repair` the solution with. Boxes left empty are dropped.
+    '''
+    displaced = []
+    boxes = []
+    for box in self.boxes.values():
+      fitting = []
+      for rect in box.rects.values():
+        if rect.get_x() + rect.get_width() <= side_length and rect.get_y() + rect.get_height() <= side_length:
+          fitting.append(rect)
+        else:
+          displaced.append(rect)
+      if len(fitting) > 0:
+        resized = Box(box.id, side_length, *fitting, calc_coords=box.calc_coords, grid_unit=self.grid_unit)
+        # Rects the new box refuses, e.g. off its grid, have to be repaired like the ones that stick out
+        displaced += [r for r in fitting if r.id not in resized.rects]
+        if len(resized.rects) > 0:
+          boxes.append(resized)
+    self.side_length = side_length
+    self.boxes = { box.id: box for box in boxes }
+    return displaced
+
+  def repair(self, rects: list[Rectangle]) -> int:
+    '''
+    Places the given rects into the solution, largest first at the first spot they fit in,
+    and opens new boxes for those that don't fit anywhere. Returns the number of boxes that had to be opened.
+    Once `max_boxes` are open, the rects that are left over go into the unplaced pool instead.
+    The rects must fit into an empty box.
+    '''
+    opened = 0
+    for rect in sorted(rects, key=lambda r: r.get_area(), reverse=True):
+      if any(box.fit_rect_compress(rect) for box in self.boxes.values()):
+        continue
+      if not self.can_open_box():
+        rect.move_to(0, 0, box_id=UNPLACED_BOX_ID)
+        self.unplaced_rects[rect.id] = rect
+        continue
+      box = self.new_box(max(self.boxes.keys(), default=-1) + 1)
+      if not box.fit_rect_compress(rect):
+        raise ValueError(f"Rect {rect.id} doesn't fit into a box of side length {self.side_length}")
+      self.boxes[box.id] = box
+      opened += 1
+    return opened
+
   def to_greedy_queue(self) -> list[Rectangle]:
     '''
     Empties all the rectangles from the solution
@@ -1,5 +1,6 @@
 import logging
 from collections import Counter
+from heapq import merge
 from itertools import product, chain
 
 from .rectangle import Rectangle
@@ -16,16 +17,22 @@ class Box:
   '''ID of this box'''
   __incident_edge_count: int
   '''number of coordinates that at least 2 rectangles in this box share.'''
+  __stale_incident_edge_count: bool
+  '''Flag to indicate that only the incident edge count needs to be recalculated.'''
   __free_coords: set[tuple[int, int]]
   '''All free coordinates in this box. These are coordinates used for search space exploration.'''
   __sorted_free_coords: list[tuple[int, int]]
   '''All free coordinates in this box, sorted by x and then y.'''
   __adjacent_coordinates: set[tuple[int, int]]
   '''All coordinates adjacent to the rectangles in this box.'''
+  __stale_adjacent_coordinates: bool
+  '''Flag to indicate that only the adjacent coordinates need to be recalculated.'''
   dirty: bool = True
   '''Flag to indicate that the adjacent coordinates need to be recalculated.'''
   calc_coords: bool
   '''Flag to skip adjacent coordinate and free coordinate calculation.'''
+  grid_unit: int
+  '''Rects may only be placed at coordinates that are multiples of this, like the slots of a pallet.'''
 
   needs_redraw: bool
   '''Signifies the drawing method that this box has changed and needs to be redrawn'''
@@ -35,13 +42,14 @@ class Box:
     s += ' '.join([str(r) for r in self.rects.values()])
     return s
 
-  def __init__(self, b_id: int, side_length: int, *rects: Rectangle, calc_coords: bool = True):
+  def __init__(self, b_id: int, side_length: int, *rects: Rectangle, calc_coords: bool = True, grid_unit: int = 1):
     '''
     Initializes a new box with a number of rects.
     Set `no_coords` to True if you want to skip adjacent / free coordinate calculation
     '''
     self.id = b_id
     self.side_length = side_length
+    self.grid_unit = grid_unit
     self.rects = {}
 
     self.calc_coords = calc_coords
@@ -50,6 +58,8 @@ class Box:
       self.__free_coords = set(product(range(side_length), range(side_length)))
 
     self.__incident_edge_count = 0
+    self.__stale_incident_edge_count = False
+    self.__stale_adjacent_coordinates = False
     self.dirty = True
     self.needs_redraw = True
 
@@ -65,8 +75,19 @@ class Box:
       rect.set_box_id(b_id)
 
 
+  def is_on_grid(self, x: int, y: int) -> bool:
+    '''Checks whether a rect may be placed with its origin at the given coordinate'''
+    return x % self.grid_unit == 0 and y % self.grid_unit == 0
+
+  def snap_to_grid(self, value: int) -> int:
+    '''Rounds a coordinate down to the closest one on the grid'''
+    return value - value % self.grid_unit
+
   def add_rect(self, rect: Rectangle) -> bool:
     '''Tries to place a rectangle within this box. Will return false if unsuccessful.'''
+    # Origins off the grid are never feasible, no matter whether the box tracks coordinates
+    if not self.is_on_grid(rect.get_x(), rect.get_y()):
+      return False
     self.recalculate_stats()
     # If rects coordinates are not part of free cords, this won't fit
     if self.calc_coords and not rect.get_all_coordinates() <= self.__free_coords:
@@ -79,17 +100,15 @@ class Box:
 
     # Update coordinates if needed
     if self.calc_coords:
-      # Update incident edge count
-      adj_coords = self.__adjacent_coordinates
-      rect_edges = rect.get_edges()
-      self.__incident_edge_count += len(rect_edges & adj_coords)
-
-      # Update adjacent coordinate set
-      self.__adjacent_coordinates ^= rect.get_edges()
-
-      # Update free coordinates
-      self.__free_coords -= rect.get_all_coordinates()
-      self.__sorted_free_coords = sorted(self.__free_coords, key=lambda coord: (coord[0], coord[1]))
+      # Xor-ing the new edges would also remove corners of other rects lying on them, which
+      # can't be told apart from plain shared edges, so like on removal both are recounted lazily
+      self.__stale_adjacent_coordinates = True
+      self.__stale_incident_edge_count = True
+
+      # Update free coordinates, filtering keeps the sorted list in order without sorting it again
+      covered = rect.get_all_coordinates()
+      self.__free_coords -= covered
+      self.__sorted_free_coords = [coord for coord in self.__sorted_free_coords if coord not in covered]
     # When working without coordinate sets, just set dirty bit
     else:
       self.dirty = True
@@ -153,6 +172,8 @@ class Box:
 
     # check if the rectangle would fit.
     for x, y in free_coords:
+      if not self.is_on_grid(x, y):
+        continue
       # does free_coords contain all the coordinates of the rectangle?
       if can_place(x, y, rect_width, rect_height):
         if apply_insertion:
@@ -169,21 +190,98 @@ class Box:
     return False
 
   def remove_rect(self, rect_id: int) -> Rectangle:
-    '''Removes a rectangle from this box.'''
+    '''
+    Removes a rectangle from this box and returns it, or `None` if it isn't in this box.
+    Together with `add_rect`, this is the only way rects should enter or leave a box,
+    so the free coordinates stay in sync with the rects.
+    '''
     self.recalculate_stats()
 
     #check if rect is in box
     if rect_id not in self.rects:
       return None
 
+    # Remove rect from internal dict
+    rect = self.rects.pop(rect_id)
+
     if self.calc_coords:
-      # Update adjacent coordinate set
-      self.__adjacent_coordinates ^= self.rects[rect_id].get_edges()
+      # Give the covered coordinates back, merging keeps the sorted list in order.
+      # Rects in a box with coordinates never overlap, so none of them can still be covered by another rect
+      freed = rect.get_all_coordinates()
+      self.__free_coords |= freed
+      self.__sorted_free_coords = list(merge(self.__sorted_free_coords, sorted(freed)))
+
+      # Corners shared with other rects can't be told apart from the removed rect's own ones,
+      # so both the adjacent coordinates and incident edges are recounted lazily
+      self.__stale_adjacent_coordinates = True
+      self.__stale_incident_edge_count = True
+    else:
+      self.dirty = True
 
-    self.dirty = True
     self.needs_redraw = True
-    # Remove rect from internal dict
-    return self.rects.pop(rect_id)
+    return rect
+
+  def skyline(self) -> list[int]:
+    '''
+    Returns the skyline profile of this box. For each x coordinate, this is
+    the highest `y + height` of any rect covering that column, or 0 if it is empty.
+    '''
+    profile = [0] * self.side_length
+    for rect in self.rects.values():
+      top = rect.get_y() + rect.get_height()
+      for x in range(rect.get_x(), min(rect.get_x() + rect.get_width(), self.side_length)):
+        profile[x] = max(profile[x], top)
+    return profile
+
+  def get_fill_ratio(self) -> float:
+    '''Returns the fraction of this box's area that is covered by rects'''
+    return sum(r.get_area() for r in self.rects.values()) / self.side_length**2
+
+  def max_additional_fit(self, candidates: list[tuple[int, int]]) -> int:
+    '''
+    Estimates how many of the given `(width, height)` sizes could still be packed into this box.
+    Each size is used at most once, they are placed largest first at the first spot they fit in.
+    The box itself is left untouched.
+    '''
+    scratch_box = Box(self.id, self.side_length, *[r.copy() for r in self.rects.values()], grid_unit=self.grid_unit)
+    fits = 0
+    for n, (width, height) in enumerate(sorted(candidates, key=lambda c: c[0] * c[1], reverse=True)):
+      # Negative ids can't collide with the ones of actual rects
+      if scratch_box.fit_rect_compress(Rectangle(0, 0, width, height, -n - 1)):
+        fits += 1
+    return fits
+
+  def is_guillotine_cuttable(self) -> bool:
+    '''
+    Checks whether the rects of this box can be separated by a sequence of straight cuts,
+    each running from one edge of the current piece to the opposite one.
+    '''
+    return Box.__is_guillotine_cuttable(list(self.rects.values()))
+
+  @staticmethod
+  def __is_guillotine_cuttable(rects: list[Rectangle]) -> bool:
+    '''
+    Recursively looks for a full cut that splits the rects into two non-empty groups.
+    Only the far edges of the rects can be such cuts, a cut is valid if it doesn't cross any rect.
+    '''
+    if len(rects) <= 1:
+      return True
+
+    # Vertical cuts at x, then horizontal cuts at y
+    for origin, extent in [
+      (Rectangle.get_x, Rectangle.get_width),
+      (Rectangle.get_y, Rectangle.get_height)
+    ]:
+      for cut in sorted({origin(r) + extent(r) for r in rects}):
+        if any(origin(r) < cut < origin(r) + extent(r) for r in rects):
+          continue
+        before = [r for r in rects if origin(r) + extent(r) <= cut]
+        after = [r for r in rects if origin(r) >= cut]
+        if len(before) == 0 or len(after) == 0:
+          continue
+        # One valid cut is enough, both pieces are independent from here on
+        return Box.__is_guillotine_cuttable(before) and Box.__is_guillotine_cuttable(after)
+    return False
 
   def get_free_coordinates(self, sort: bool=False) -> set[tuple[int, int]]:
     '''Returns all currently free x/y coordinates in this box.
@@ -199,6 +297,7 @@ class Box:
     Returns all coordinates adjacent to the rectangles in this box.
     '''
     self.recalculate_stats()
+    self.__refresh_adjacent_coordinates()
     return self.__adjacent_coordinates
 
   def get_incident_edge_count(self) -> int:
@@ -207,6 +306,8 @@ class Box:
     '''
     if self.dirty:
       self.recalculate_stats()
+    if self.__stale_incident_edge_count:
+      self.__recalculate_incident_edge_count()
     return self.__incident_edge_count
 
   def recalculate_stats(self):
@@ -217,9 +318,16 @@ class Box:
       if self.calc_coords:
         self.__recalculate_adjacent_coordinates()
         self.__recalculate_free_coordinates()
+        self.__stale_adjacent_coordinates = False
       self.__recalculate_incident_edge_count()
       self.dirty = False
 
+  def __refresh_adjacent_coordinates(self):
+    '''Recalculates the adjacent coordinates if a removal left them stale.'''
+    if self.__stale_adjacent_coordinates:
+      self.__recalculate_adjacent_coordinates()
+      self.__stale_adjacent_coordinates = False
+
   def __recalculate_free_coordinates(self):
     '''
     Recalculates the free coordinates in this box.
@@ -254,6 +362,7 @@ class Box:
     Recalculates the number of adjacent edge coordinates of the rectangles in this box.
     '''
     self.__incident_edge_count = 0
+    self.__stale_incident_edge_count = False
 
     # Count edge coordinate occurrences in a map of (coordinate -> count)
     edge_count = Counter()
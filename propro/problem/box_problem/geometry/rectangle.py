@@ -3,6 +3,7 @@ Contains the Rectangle class for the box-rect problem
 '''
 from __future__ import annotations
 from itertools import product, chain
+from typing import Optional
 
 class Rectangle:
   '''
@@ -25,17 +26,42 @@ class Rectangle:
   __placeable_edges: set[tuple[int, int]]
   '''Set of the bottom & right edges of this rect'''
 
+  value: int
+  '''Value of packing this rect, used by the knapsack variant. Defaults to the rect's area.'''
+
   highlighted: bool
   '''Flag to draw this rect in a different color'''
-
-  def __init__(self, x: int, y: int, w: int, h: int, i: int, box_id: int = None):
+  flipped: bool
+  '''Whether this rect is rotated from its original orientation'''
+  splittable: bool
+  '''Whether the application allows cutting this rect into smaller pieces'''
+  parent_id: Optional[int]
+  '''ID of the rect this one was cut from, `None` if it wasn't'''
+
+  def __init__(
+      self,
+      x: int,
+      y: int,
+      w: int,
+      h: int,
+      i: int,
+      box_id: int = None,
+      value: int = None,
+      flipped: bool = False,
+      splittable: bool = False,
+      parent_id: Optional[int] = None
+    ):
     self.__x = x
     self.__y = y
     self.width = w
     self.height = h
     self.id = i
     self.box_id = box_id
+    self.value = value if value is not None else w * h
     self.highlighted = False
+    self.flipped = flipped
+    self.splittable = splittable
+    self.parent_id = parent_id
     self.__placeable_edges = set()
     self.__edges = set()
     self.__is_dirty_coordinates = True
@@ -55,7 +81,17 @@ class Rectangle:
     '''Create a deep copy of this rectangle'''
     if self.__is_dirty():
       self.__recompute()
-    return Rectangle(self.__x, self.__y, self.width, self.height, self.id)
+    return Rectangle(
+      self.__x,
+      self.__y,
+      self.width,
+      self.height,
+      self.id,
+      value=self.value,
+      flipped=self.flipped,
+      splittable=self.splittable,
+      parent_id=self.parent_id
+    )
 
   def get_area(self) -> int:
     '''Compute area of the rectangle'''
@@ -134,11 +170,13 @@ class Rectangle:
     if permissible_overlap == 0.0:
       return True
 
-    # Calculate overlap area
+    return (self.overlap_area(other) / max(self.get_area(), other.get_area())) > permissible_overlap
+
+  def overlap_area(self, other: Rectangle) -> int:
+    '''Computes the area that is covered by both rectangles'''
     overlap_x = max(0, min(self.get_x() + self.width, other.get_x() + other.get_width()) - max(self.get_x(), other.get_x()))
     overlap_y = max(0, min(self.get_y() + self.height, other.get_y() + other.height) - max(self.get_y(), other.get_y()))
-
-    return (overlap_x * overlap_y / max(self.get_area(), other.get_area())) > permissible_overlap
+    return overlap_x * overlap_y
 
   def get_corners(self) -> set[tuple[int, int]]:
     '''Returns a set of coordinates for each corner of the rectangle'''
@@ -229,9 +267,22 @@ class Rectangle:
   def flip(self):
     '''Flip the rectangle'''
     self.width, self.height = self.height, self.width
+    self.flipped = not self.flipped
     self.__is_dirty_coordinates = True
     self.__is_dirty_edges = True
 
+  def orient(self, width: int, height: int) -> bool:
+    '''
+    Flips the rectangle if needed so it has the given width and height.
+    Returns whether the rectangle was flipped.
+    '''
+    if (self.width, self.height) == (width, height):
+      return False
+    if (self.height, self.width) == (width, height):
+      self.flip()
+      return True
+    raise ValueError(f"Rectangle {self} can't be oriented to {width}x{height}")
+
   # NOTE: Tried an alternative move_by method for relative offset, but it ended up being slower
   def move_to(self, new_x: int, new_y: int, box_id: int = None):
     '''Will move the rect to a new origin.'''
@@ -0,0 +1,113 @@
+from __future__ import annotations
+from typing import Optional
+
+from .box_problem import BoxProblem
+
+class ProblemBuilder:
+  '''
+  Collects the parameters of a randomly generated instance one by one and checks them together.
+  Every setter returns the builder, so they can be chained:
+  `ProblemBuilder().box_length(15).rect_count(50).widths(range(1, 10)).heights(range(1, 10)).build()`
+  '''
+  __box_length: Optional[int]
+  __rect_count: Optional[int]
+  __widths: Optional[range]
+  __heights: Optional[range]
+  __values: Optional[range]
+  __seed: Optional[int]
+  __grid_unit: int
+  __max_boxes: Optional[int]
+
+  def __init__(self):
+    self.__box_length = None
+    self.__rect_count = None
+    self.__widths = None
+    self.__heights = None
+    self.__values = None
+    self.__seed = None
+    self.__grid_unit = 1
+    self.__max_boxes = None
+
+  def box_length(self, box_length: int) -> ProblemBuilder:
+    '''Side length of the boxes, required'''
+    self.__box_length = box_length
+    return self
+
+  def rect_count(self, rect_count: int) -> ProblemBuilder:
+    '''Number of rects to generate, required'''
+    self.__rect_count = rect_count
+    return self
+
+  def widths(self, widths: range) -> ProblemBuilder:
+    '''Range the width of every rect is drawn from, required'''
+    self.__widths = widths
+    return self
+
+  def heights(self, heights: range) -> ProblemBuilder:
+    '''Range the height of every rect is drawn from, required'''
+    self.__heights = heights
+    return self
+
+  def values(self, values: range) -> ProblemBuilder:
+    '''Range the value of every rect is drawn from, by default the area of a rect is its value'''
+    self.__values = values
+    return self
+
+  def seed(self, seed: int) -> ProblemBuilder:
+    '''Seed to generate the rects with, by default a random one is picked'''
+    self.__seed = seed
+    return self
+
+  def grid_unit(self, grid_unit: int) -> ProblemBuilder:
+    '''Unit that the coordinates of all rects have to be multiples of, 1 by default'''
+    self.__grid_unit = grid_unit
+    return self
+
+  def max_boxes(self, max_boxes: Optional[int]) -> ProblemBuilder:
+    '''Number of boxes that are available, as many as needed by default'''
+    self.__max_boxes = max_boxes
+    return self
+
+  def validate(self):
+    '''Raises a ValueError describing the first parameter that is missing or doesn't go with the others'''
+    for (name, value) in [
+      ("box length", self.__box_length),
+      ("rect count", self.__rect_count),
+      ("width range", self.__widths),
+      ("height range", self.__heights)
+    ]:
+      if value is None:
+        raise ValueError(f"The {name} of the problem is missing")
+
+    if self.__box_length < 1:
+      raise ValueError("Box length must be at least 1")
+    if self.__rect_count < 0:
+      raise ValueError("Rect count must not be negative")
+    for (name, dimensions) in [("width", self.__widths), ("height", self.__heights)]:
+      if len(dimensions) == 0:
+        raise ValueError(f"The {name} range is empty")
+      if min(dimensions) < 1:
+        raise ValueError(f"Rects must have a positive {name}, the range starts at {min(dimensions)}")
+    if self.__values is not None and len(self.__values) == 0:
+      raise ValueError("The value range is empty")
+    # Flipping a rect doesn't change whether both of its sides fit
+    if min(self.__widths) > self.__box_length or min(self.__heights) > self.__box_length:
+      raise ValueError(f"None of the rects could ever fit into a box of side length {self.__box_length}")
+    if self.__grid_unit < 1:
+      raise ValueError("Grid unit must be at least 1")
+    if self.__max_boxes is not None and self.__max_boxes < 1:
+      raise ValueError("Maximum box count must be at least 1")
+
+  def build(self) -> BoxProblem:
+    '''Validates the parameters and generates the instance, raises a ValueError if they don't make sense'''
+    self.validate()
+    return BoxProblem(
+      box_length=self.__box_length,
+      n_rect=self.__rect_count,
+      w_range=self.__widths,
+      h_range=self.__heights,
+      v_range=self.__values,
+      seed=self.__seed,
+      grid_unit=self.__grid_unit,
+      max_boxes=self.__max_boxes
+    )
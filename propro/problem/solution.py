@@ -21,3 +21,15 @@ class Solution(ABC):
     '''
     Deconstructs this solution into something empty and returns a list of objects to be processed.
     '''
+
+  def get_order(self) -> Any:
+    '''
+    Returns the order the parts of this solution are kept in, for `restore_order`.
+    Undoing a move restores the content of a solution, but not necessarily this order.
+    '''
+    return None
+
+  def restore_order(self, order: Any):
+    '''
+    Brings back an order returned by `get_order`, the content of the solution has to be the same as back then.
+    '''
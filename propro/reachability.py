@@ -0,0 +1,78 @@
+'''
+Diagnostic of which scores every neighborhood can reach from a solution in a single move,
+e.g. to see why one neighborhood gets out of a local optimum and another one doesn't.
+'''
+
+import sys
+from argparse import ArgumentParser
+
+from rich.table import Table
+from rich.console import Console
+
+from cli import add_log_args, configure_logging
+from checkpoint import read_solution
+from instances import read_problem
+from modes import Neighborhood
+from problem.box_problem.box_heuristic import GenericHeuristic
+
+def format_score(score: GenericHeuristic) -> str:
+  '''Formats a score as unplaced rects / box count / entropy / incident edges'''
+  return f"{score.unplaced_count} / {score.box_count} / {score.box_entropy:.3f} / {score.incident_edges}"
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--instance",
+    type=str,
+    help="Instance file as written by instances.py",
+    required=True
+  )
+  parser.add_argument(
+    "--solution",
+    type=str,
+    help="Solution file of the instance, e.g. a checkpoint, otherwise every rect starts in its own box"
+  )
+  parser.add_argument(
+    "--neighborhood",
+    type=str,
+    nargs="+",
+    help=f"Neighborhoods to compare, all by default: {[n.__name__ for n in Neighborhood.__subclasses__()]}"
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  configure_logging(args)
+
+  neighborhoods = Neighborhood.__subclasses__()
+  if args.neighborhood is not None:
+    unknown = set(args.neighborhood) - {n.__name__ for n in neighborhoods}
+    if len(unknown) > 0:
+      sys.exit(f"Unknown neighborhoods: {', '.join(sorted(unknown))}")
+    neighborhoods = [n for n in neighborhoods if n.__name__ in args.neighborhood]
+
+  problem = read_problem(args.instance)
+  if args.solution is not None:
+    problem.current_solution = read_solution(args.solution)
+  current_score = problem.current_solution.get_heuristic_score()
+
+  # Per neighborhood (Name / Neighbors / Distinct scores / Box counts / Best score / Improving neighbors)
+  table = Table("Neighborhood", "Neighbors", "Distinct scores", "Box counts", "Best score", "Improving")
+  for Mode in neighborhoods:
+    scores = problem.reachable_scores(Mode)
+    box_counts: dict[int, int] = {}
+    for (score, count) in scores:
+      box_counts[score.box_count] = box_counts.get(score.box_count, 0) + count
+    table.add_row(
+      Mode.__name__,
+      str(sum(count for (_, count) in scores)),
+      str(len(scores)),
+      ", ".join(f"{box_count}: {count}" for box_count, count in sorted(box_counts.items())),
+      format_score(scores[0][0]) if len(scores) > 0 else "-",
+      str(sum(count for (score, count) in scores if score < current_score))
+    )
+
+  console = Console()
+  console.print(f"Current score (unplaced / boxes / entropy / edges): {format_score(current_score)}")
+  console.print(table)
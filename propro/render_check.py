@@ -0,0 +1,95 @@
+'''
+Snapshot check of the solution layout, to catch unintended changes to the drawing while it gets refactored.
+Paints a fixed, seeded solution with different options and compares a hash of the draw commands
+to the one stored in the reference file.
+'''
+
+import hashlib
+import json
+import os
+import sys
+from argparse import ArgumentParser
+
+from rich.table import Table
+from rich.console import Console
+
+from modes import Permutation
+from problem.box_problem.box_problem import BoxProblem
+from problem.box_problem.box_solution import BoxSolution
+from rendering import paint_solution, COLOR_BLIND_PALETTE
+
+REFERENCE_FILE = os.path.join(os.path.dirname(__file__), "render_reference.json")
+'''Hashes of the draw commands of every case as of the last intended change, next to this script'''
+
+CASES = {
+  "plain": dict(erase=True),
+  "rotation_capacity": dict(erase=True, show_rotation=True, show_capacity=True),
+  "color_blind": dict(erase=True, palette=COLOR_BLIND_PALETTE),
+  "low_detail": dict(erase=True, min_detail_size=100),
+  "clipped": dict(erase=True, clip=(0, 0, 40, 40))
+}
+'''Options `paint_solution` is called with, by name of the case'''
+
+def snapshot_solution(seed: int) -> BoxSolution:
+  '''
+  Solution to paint, which only depends on the seed. The permutation decoding places rects the same way every time
+  and flips some of them, so the rotation markers show up as well.
+  '''
+  problem = BoxProblem(box_length=15, n_rect=40, w_range=range(1, 10), h_range=range(1, 10), seed=seed)
+  return Permutation.initialize(problem.current_solution)
+
+def command_hash(solution: BoxSolution, scaling_factor: float, options: dict) -> str:
+  '''Paints the solution and hashes the resulting draw commands'''
+  commands = paint_solution(solution, scaling_factor, **options)
+  return hashlib.sha256(repr(commands).encode("utf-8")).hexdigest()
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--reference",
+    type=str,
+    help="JSON file with the hash of every case",
+    default=REFERENCE_FILE
+  )
+  parser.add_argument(
+    "--update",
+    action="store_true",
+    help="Write the current hashes to the reference file instead of comparing them, after an intended change"
+  )
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="Seed of the painted instance, the reference only holds for its default",
+    default=2
+  )
+
+  args = parser.parse_args()
+
+  hashes = {}
+  for name, options in CASES.items():
+    # Painting resets the redraw flags, so every case starts from a fresh solution
+    hashes[name] = command_hash(snapshot_solution(args.seed), 2, options)
+
+  if args.update:
+    with open(args.reference, "w", encoding="utf-8") as f:
+      json.dump(hashes, f, indent=2)
+      f.write("\n")
+    sys.exit(0)
+
+  with open(args.reference, "r", encoding="utf-8") as f:
+    reference = json.load(f)
+
+  # Per case (Case / Hash / Matches)
+  table = Table("Case", "Hash", "Matches")
+  failures = []
+  for name, digest in hashes.items():
+    matches = reference.get(name) == digest
+    if not matches:
+      failures.append(f"Case {name}: draw commands changed, expected {reference.get(name)}, got {digest}")
+    table.add_row(name, digest[:12], "yes" if matches else "no")
+
+  console = Console()
+  console.print(table)
+  if len(failures) > 0:
+    sys.exit("\n".join(failures))
@@ -0,0 +1,240 @@
+'''
+Layout of a solution as a list of draw commands, independent of the gui toolkit.
+'''
+
+from dataclasses import dataclass
+from math import sqrt, floor, ceil
+from typing import Optional
+
+from problem.box_problem.box_solution import BoxSolution
+from problem.box_problem.geometry import Rectangle
+
+BOX_SPACING = 0.5
+
+@dataclass(frozen=True)
+class RectStyle:
+  '''How a single rect is painted, see `paint_rect`'''
+  fill: str
+  '''Fill of the rect'''
+  marker: str
+  '''Color of the rotation marker and the label'''
+
+@dataclass(frozen=True)
+class Palette:
+  '''Colors a solution is painted with'''
+  box: str
+  '''Fill of the empty box'''
+  rect: str
+  '''Fill of a rect'''
+  highlight: str
+  '''Fill of a rect that was just moved'''
+  search_space: str
+  '''Dots of the coordinates a neighborhood tries'''
+  annotation: str
+  '''Rotation lines and capacity labels'''
+  occupancy: tuple[str, ...]
+  '''Fills of boxes too small to show their rects, from empty to full'''
+
+  def occupancy_color(self, fill_ratio: float) -> str:
+    '''Picks the fill for a box with the given fraction of its area covered'''
+    return self.occupancy[min(len(self.occupancy) - 1, floor(fill_ratio * len(self.occupancy)))]
+
+  def rect_style(self, highlighted: bool = False) -> RectStyle:
+    '''Style of a rect, in the highlight color if it was just moved'''
+    return RectStyle(fill=self.highlight if highlighted else self.rect, marker=self.annotation)
+
+DEFAULT_PALETTE = Palette(
+  box='gray',
+  rect='red',
+  highlight='blue',
+  search_space='blue',
+  annotation='black',
+  occupancy=('#FEE5D9', '#FCAE91', '#FB6A4A', '#DE2D26', '#A50F15')
+)
+
+COLOR_BLIND_PALETTE = Palette(
+  box='#BBBBBB',
+  rect='#E69F00',
+  highlight='#0072B2',
+  search_space='#009E73',
+  annotation='black',
+  occupancy=('#FDE725', '#5DC863', '#21908C', '#3B528B', '#440154')
+)
+'''
+Colors of the Okabe-Ito palette, which stay distinguishable with the common kinds of color blindness.
+The occupancy goes along the viridis scale, which only changes in lightness for them.
+'''
+
+@dataclass(frozen=True)
+class DrawRectangle:
+  '''Command to draw a filled rectangle'''
+  top_left: tuple[float, float]
+  bottom_right: tuple[float, float]
+  fill_color: str
+
+  def draw(self, graph):
+    '''Draws this rectangle on a FreeSimpleGUI graph'''
+    graph.draw_rectangle(top_left=self.top_left, bottom_right=self.bottom_right, fill_color=self.fill_color)
+
+@dataclass(frozen=True)
+class DrawLine:
+  '''Command to draw a line'''
+  point_from: tuple[float, float]
+  point_to: tuple[float, float]
+  color: str
+
+  def draw(self, graph):
+    '''Draws this line on a FreeSimpleGUI graph'''
+    graph.draw_line(point_from=self.point_from, point_to=self.point_to, color=self.color)
+
+@dataclass(frozen=True)
+class DrawText:
+  '''Command to draw a text'''
+  text: str
+  location: tuple[float, float]
+  color: str
+
+  def draw(self, graph):
+    '''Draws this text on a FreeSimpleGUI graph'''
+    graph.draw_text(self.text, location=self.location, color=self.color)
+
+DrawCommand = DrawRectangle | DrawLine | DrawText
+
+def paint_rect(
+    rect: Rectangle,
+    top_left: tuple[float, float],
+    scaling_factor: float,
+    style: RectStyle,
+    rotated: bool = False,
+    label: Optional[str] = None
+  ) -> list[DrawCommand]:
+  '''
+  Lays out a single rect with its top left corner at the given position.
+  With `rotated`, a diagonal line marks it as flipped, and a `label` is written into its center.
+  Every way of painting a rect goes through here, so other shapes only need to be handled in one place.
+  '''
+  (left, top) = top_left
+  bottom_right = (left + rect.get_width() * scaling_factor, top + rect.get_height() * scaling_factor)
+  commands: list[DrawCommand] = [DrawRectangle(top_left=top_left, bottom_right=bottom_right, fill_color=style.fill)]
+  if rotated:
+    commands.append(DrawLine(point_from=top_left, point_to=bottom_right, color=style.marker))
+  if label is not None:
+    commands.append(DrawText(
+      text=label,
+      location=((left + bottom_right[0]) / 2, (top + bottom_right[1]) / 2),
+      color=style.marker
+    ))
+  return commands
+
+def solution_extent(solution: BoxSolution, scaling_factor: float) -> tuple[float, float]:
+  '''Width and height of the area that `paint_solution` lays the given solution out in'''
+  if len(solution.boxes) == 0:
+    return (0, 0)
+  boxes_per_row = floor(sqrt(len(solution.boxes)))
+  rows = boxes_per_row
+  cols = ceil(len(solution.boxes) / boxes_per_row)
+  box_extent = (solution.side_length + BOX_SPACING) * scaling_factor
+  return (cols * box_extent, rows * box_extent)
+
+def paint_solution(
+    solution: BoxSolution,
+    scaling_factor: float,
+    erase: bool = False,
+    show_rotation: bool = False,
+    show_capacity: bool = False,
+    palette: Palette = DEFAULT_PALETTE,
+    min_detail_size: float = 0,
+    clip: Optional[tuple[float, float, float, float]] = None
+  ) -> list[DrawCommand]:
+  '''
+  Lays out the given box problem solution as a list of draw commands.
+  Only boxes that need a redraw are included, unless `erase` is set.
+  With `show_rotation`, rects that are flipped from their original orientation get a diagonal line.
+  With `show_capacity`, every box is labelled with the number of rects from other boxes that would still fit in.
+  Since that changes with every other box, the labels are only up to date if everything is painted with `erase`.
+  All colors are taken from the given palette.
+  For large solutions, boxes that come out smaller than `min_detail_size` are painted as a single rectangle
+  colored by how full they are, and boxes entirely outside of the `(left, top, right, bottom)` clip area are left out.
+  '''
+  commands = []
+  all_rects = [r for b in solution.boxes.values() for r in b.rects.values()]
+
+  # Pre-calculate constants
+  boxes_per_row = floor(sqrt(len(solution.boxes)))
+  scaled_side_length = solution.side_length * scaling_factor
+  scaled_spacing = BOX_SPACING * scaling_factor
+
+  # Draw the boxes
+  for box_idx, box in enumerate(list(solution.boxes.values())):
+
+    # Skip box if it doesn't need to be drawn again
+    #  unless we erased the whole graph before
+    if not (box.needs_redraw or erase):
+      continue
+
+    row = box_idx % boxes_per_row
+    col = floor(box_idx / boxes_per_row)
+
+    box_left = col * (scaled_side_length + scaled_spacing)
+    box_top = row * (scaled_side_length + scaled_spacing)
+
+    # Nothing to see of boxes outside of the clip area, so they stay due for a redraw
+    if clip is not None:
+      (clip_left, clip_top, clip_right, clip_bottom) = clip
+      if box_left > clip_right or box_top > clip_bottom \
+          or box_left + scaled_side_length < clip_left or box_top + scaled_side_length < clip_top:
+        continue
+    box.needs_redraw = False
+
+    # Too small for the rects to be told apart, so only show how full the box is
+    if scaled_side_length < min_detail_size:
+      for rect in box.rects.values():
+        rect.highlighted = False
+      commands.append(DrawRectangle(
+        top_left=(box_left, box_top),
+        bottom_right=(box_left + scaled_side_length, box_top + scaled_side_length),
+        fill_color=palette.occupancy_color(box.get_fill_ratio())
+      ))
+      continue
+
+    # Draw box
+    commands.append(DrawRectangle(
+      top_left=(box_left, box_top),
+      bottom_right=(box_left + scaled_side_length, box_top + scaled_side_length),
+      fill_color=palette.box
+    ))
+
+    # Also paint the box's rectangles
+    for rect in list(box.rects.values()):
+      style = palette.rect_style(rect.highlighted)
+      if rect.highlighted:
+        # Clear highlighted flag, redraw box in next iteration
+        rect.highlighted = False
+        box.needs_redraw = True
+      commands += paint_rect(
+        rect,
+        (box_left + rect.get_x() * scaling_factor, box_top + rect.get_y() * scaling_factor),
+        scaling_factor,
+        style,
+        rotated=show_rotation and rect.flipped
+      )
+
+    # Paint the box's free coordinate search space
+    for (x, y) in sorted(box.get_adjacent_coordinates()):
+      dot_left = box_left + (x - 0.1) * scaling_factor
+      dot_top = box_top + (y - 0.1) * scaling_factor
+      commands.append(DrawRectangle(
+        top_left=(dot_left, dot_top),
+        bottom_right=(dot_left + 0.2 * scaling_factor, dot_top + 0.2 * scaling_factor),
+        fill_color=palette.search_space
+      ))
+
+    if show_capacity:
+      capacity = box.max_additional_fit([(r.width, r.height) for r in all_rects if r.box_id != box.id])
+      commands.append(DrawText(
+        text=str(capacity),
+        location=(box_left + scaled_side_length / 2, box_top + scaled_side_length / 2),
+        color=palette.annotation
+      ))
+
+  return commands
@@ -0,0 +1,304 @@
+'''
+Headless runner for a single algorithm / mode combination.
+'''
+
+import logging
+import random
+import signal
+import sys
+from argparse import ArgumentParser, ArgumentTypeError
+from dataclasses import dataclass
+from typing import Optional
+
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
+from algorithms import OptimizationAlgorithm, AcceptanceDecision, LocalSearch, MemeticAlgorithm, TieBreak, get_algo_by_name
+from modes import Neighborhood, NeighborhoodCache, Geometric, GeometricOverlap, CoarseToFine, LARGE_NEIGHBORHOOD_SIZE, get_available_modes, get_mode_by_name
+from problem.box_problem.problem_builder import ProblemBuilder
+from problem.box_problem.box_solution import BoxSolution
+from checkpoint import BestSolutionCheckpoint
+from stop_conditions import StopCondition, Stagnation, ScoreAtMost, Cancelled
+from plotting import export_convergence_plot
+
+logger = logging.getLogger(__name__)
+
+@dataclass
+class RunResult:
+  '''Outcome of a headless run'''
+  best_solution: BoxSolution
+  '''Best solution found during the run'''
+  ticks: int
+  '''Number of ticks the algorithm ran for'''
+  stop_reason: Optional[StopCondition]
+  '''The condition that stopped the run, `None` if the tick budget ran out'''
+  box_count_history: list[int]
+  '''Box count of the current solution before the first and after every tick'''
+
+def int_at_least(minimum: int):
+  '''Argument type for whole numbers of at least `minimum`, so argparse reports anything below it'''
+  def parse(value: str) -> int:
+    try:
+      number = int(value)
+    except ValueError:
+      raise ArgumentTypeError(f"expected a whole number, got '{value}'") from None
+    if number < minimum:
+      raise ArgumentTypeError(f"must be at least {minimum}, got {number}")
+    return number
+  return parse
+
+def log_acceptance_to_stderr(decision: AcceptanceDecision):
+  '''Acceptance observer which prints every decision to stderr'''
+  print(decision, file=sys.stderr)
+
+def cancel_on_interrupt(cancellation: Cancelled):
+  '''
+  Makes Ctrl-C stop the run after the current tick instead of aborting it, so the best solution is kept.
+  A second Ctrl-C aborts right away.
+  '''
+  def handle_interrupt(_signum, _frame):
+    print("Interrupted, stopping after the current tick (press Ctrl-C again to abort)", file=sys.stderr)
+    cancellation.cancel()
+    signal.signal(signal.SIGINT, signal.default_int_handler)
+  signal.signal(signal.SIGINT, handle_interrupt)
+
+def run(
+    algorithm: OptimizationAlgorithm,
+    max_ticks: int,
+    checkpoint: BestSolutionCheckpoint = None,
+    stop_conditions: list[StopCondition] = None
+  ) -> RunResult:
+  '''
+  Runs the algorithm until either `max_ticks` is reached or one of the stop conditions is met.
+  By default, the run stops once the score has not changed for five ticks.
+  '''
+  if checkpoint is None:
+    checkpoint = BestSolutionCheckpoint()
+  if stop_conditions is None:
+    stop_conditions = [Stagnation()]
+
+  if algorithm.has_complete_solution():
+    checkpoint.update(algorithm.problem.current_solution)
+  box_count_history = [algorithm.problem.current_solution.get_heuristic_score().box_count]
+  algorithm.record_progress()
+
+  ticks = 0
+  stop_reason = None
+  while ticks < max_ticks and stop_reason is None:
+    logger.info("Iteration %i", ticks)
+    algorithm.tick()
+    algorithm.check_lower_bound()
+    algorithm.record_progress()
+    box_count_history.append(algorithm.problem.current_solution.get_heuristic_score().box_count)
+    ticks += 1
+
+    # Partial solutions of constructive algorithms are no candidates for the best one
+    if algorithm.has_complete_solution():
+      checkpoint.update(algorithm.problem.current_solution)
+
+    # Check every condition, since some of them keep track of the scores
+    met_conditions = [c for c in stop_conditions if c.is_met(algorithm)]
+    if len(met_conditions) > 0:
+      stop_reason = met_conditions[0]
+
+  # Make sure the latest improvement ends up on disk, even if it was throttled
+  checkpoint.flush()
+  return RunResult(checkpoint.best_solution, ticks, stop_reason, box_count_history)
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+  parser.add_argument(
+    "--algorithm",
+    type=str,
+    help=f"Possible values: {[a.__name__ for a in OptimizationAlgorithm.__subclasses__()]}",
+    default="LocalSearch"
+  )
+  parser.add_argument(
+    "--mode",
+    type=str,
+    help=f"Possible values: {[m.__name__ for m in get_available_modes(None)]}",
+    default="Geometric"
+  )
+  add_problem_args(parser, rect_number=200, rect_size="1-10", box_length=15)
+  parser.add_argument(
+    '--tick-number',
+    type=int,
+    help="Number of ticks to run the algorithm for",
+    default=400
+  )
+  parser.add_argument(
+    "--output",
+    type=str,
+    help="Path to checkpoint the best solution to as JSON whenever it improves"
+  )
+  parser.add_argument(
+    "--plot",
+    type=str,
+    help="Path to save a plot of the box count over the ticks to, e.g. as PNG"
+  )
+  parser.add_argument(
+    "--target-boxes",
+    type=int,
+    help="Stop as soon as a solution with at most this many boxes is found"
+  )
+  parser.add_argument(
+    "--log-acceptance",
+    action="store_true",
+    help="Print every acceptance decision of the algorithm to stderr"
+  )
+  parser.add_argument(
+    "--checkpoint-interval",
+    type=float,
+    help="Minimum number of seconds between two checkpoint writes",
+    default=1.0
+  )
+  parser.add_argument(
+    "--initial-step",
+    type=int_at_least(1),
+    help="Grid step the CoarseToFine neighborhood starts with",
+    default=CoarseToFine.initial_step
+  )
+  parser.add_argument(
+    "--refinement-factor",
+    type=int_at_least(2),
+    help="Factor the CoarseToFine neighborhood divides its grid step by whenever it converged",
+    default=CoarseToFine.refinement_factor
+  )
+  parser.add_argument(
+    "--overlap-penalty",
+    type=float,
+    help="Weight of the overlap area in the score of the GeometricOverlap neighborhood, 1 makes a box worth of overlap as bad as a box",
+    default=GeometricOverlap.overlap_penalty
+  )
+  parser.add_argument(
+    "--grid-unit",
+    type=int,
+    help="Only place rects at coordinates that are multiples of this, e.g. slots of a pallet",
+    default=1
+  )
+  parser.add_argument(
+    "--max-boxes",
+    type=int,
+    help="Only this many boxes are available, rects that don't fit into them stay unplaced"
+  )
+  parser.add_argument(
+    "--tie-break",
+    type=str,
+    help=f"How the local search decides between neighbors with the same score: {[t.name for t in TieBreak]}",
+    default=TieBreak.RANDOM.name
+  )
+  parser.add_argument(
+    "--neighborhood-cache",
+    type=int,
+    help="Cache the neighbors of this many recently visited solutions"
+  )
+  parser.add_argument(
+    "--strict",
+    action="store_true",
+    help="Let the local search only move to strictly better neighbors, not to equally good ones"
+  )
+  parser.add_argument(
+    "--local-search-depth",
+    type=int,
+    help="Maximum number of local search ticks the memetic algorithm refines each individual with",
+    default=MemeticAlgorithm.local_search_depth
+  )
+  parser.add_argument(
+    "--largest-first",
+    action="store_true",
+    help="Let the Geometric neighborhood try moving large rects first"
+  )
+  parser.add_argument(
+    "--force",
+    action="store_true",
+    help=f"Run even if the neighborhood is estimated to be larger than {LARGE_NEIGHBORHOOD_SIZE:,} candidates"
+  )
+  parser.add_argument(
+    "--seed",
+    type=int,
+    help="RNG seed"
+  )
+  parser.add_argument(
+    "--shuffle-seed",
+    type=int,
+    help="Shuffle the rects of the starting solution with this seed, so restarts begin at different solutions"
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  # Fix seed for deterministic problem generation if specified
+  if args.seed is not None:
+    random.seed(args.seed)
+
+  configure_logging(args)
+
+  Geometric.largest_first = args.largest_first
+  MemeticAlgorithm.local_search_depth = args.local_search_depth
+  CoarseToFine.set_initial_step(args.initial_step)
+  CoarseToFine.set_refinement_factor(args.refinement_factor)
+  GeometricOverlap.overlap_penalty = args.overlap_penalty
+
+  Algorithm = get_algo_by_name(args.algorithm)
+  Mode = get_mode_by_name(Algorithm, args.mode)
+  if Algorithm is None or Mode is None:
+    raise ValueError(f"Unknown configuration: {args.algorithm} / {args.mode}")
+
+  # Fail cleanly on instances that no algorithm can pack
+  try:
+    optimization_problem = ProblemBuilder() \
+      .box_length(args.box_length) \
+      .rect_count(args.rect_number) \
+      .widths(parse_range(args.rect_x)) \
+      .heights(parse_range(args.rect_y)) \
+      .grid_unit(args.grid_unit) \
+      .max_boxes(args.max_boxes) \
+      .build()
+    optimization_problem.check_placeable()
+  except ValueError as e:
+    sys.exit(str(e))
+  if args.shuffle_seed is not None:
+    optimization_problem.shuffle_into_random_feasible(args.shuffle_seed)
+  optimization_algorithm = Algorithm(optimization_problem, Mode)
+  if isinstance(optimization_algorithm, LocalSearch):
+    optimization_algorithm.set_tie_break(TieBreak[args.tie_break.upper()])
+    optimization_algorithm.accept_equal = not args.strict
+
+  # Refuse configurations that would hang on the first tick
+  if issubclass(Mode, Neighborhood) and not args.force:
+    size = Mode.estimate_size(optimization_problem.current_solution)
+    if size > LARGE_NEIGHBORHOOD_SIZE:
+      sys.exit(f"Neighborhood is estimated at ~{size:,} candidates per tick, pass --force to run anyway")
+
+  if args.neighborhood_cache is not None:
+    optimization_algorithm.set_neighborhood_cache(NeighborhoodCache(args.neighborhood_cache))
+  if args.log_acceptance:
+    optimization_algorithm.add_acceptance_observer(log_acceptance_to_stderr)
+
+  cancellation = Cancelled()
+  cancel_on_interrupt(cancellation)
+  conditions = [cancellation, Stagnation()]
+  if args.target_boxes is not None:
+    conditions.append(ScoreAtMost(args.target_boxes))
+
+  result = run(
+    optimization_algorithm,
+    args.tick_number,
+    BestSolutionCheckpoint(args.output, args.checkpoint_interval),
+    conditions
+  )
+  print(f"Best score: {result.best_solution.get_heuristic_score()}")
+  print(f"Box fill: {result.best_solution.fill_stats()}")
+  print(f"Progress: {optimization_algorithm.progress}")
+  print(f"Stopped after {result.ticks} ticks ({result.stop_reason or 'tick budget exhausted'})")
+
+  if optimization_algorithm.neighborhood_cache is not None:
+    print(f"Neighborhood cache hit rate: {optimization_algorithm.neighborhood_cache.hit_rate():.1%}")
+
+  if args.plot is not None:
+    export_convergence_plot(result.box_count_history, optimization_problem.get_lower_bound(), args.plot)
+
+  # Answer the decision question if a target was given
+  if args.target_boxes is not None:
+    if result.best_solution.get_heuristic_score().box_count <= args.target_boxes:
+      print(f"Success: found a packing into {args.target_boxes} boxes or less")
+    else:
+      print(f"Failure: no packing into {args.target_boxes} boxes found within {args.tick_number} ticks")
@@ -0,0 +1,154 @@
+'''
+Sweep over instance seeds to find the ones that are hard or easy for an algorithm, e.g. for curating test sets.
+'''
+
+import logging
+import random
+import sys
+from argparse import ArgumentParser
+from dataclasses import dataclass
+from typing import Optional
+
+from rich.table import Table
+from rich.console import Console
+
+from cli import add_problem_args, add_log_args, configure_logging, parse_range
+from algorithms import OptimizationAlgorithm, get_algo_by_name
+from modes import get_available_modes, get_mode_by_name
+from problem.box_problem.box_problem import BoxProblem
+from runner import run
+from stop_conditions import Stagnation, ScoreAtMost
+
+@dataclass
+class SweepResult:
+  '''Outcome of the run on the instance of one seed'''
+  seed: int
+  lower_bound: int
+  box_count: Optional[int]
+  '''Box count of the best solution, `None` if the run found no complete one'''
+  ticks_to_bound: Optional[int]
+  '''Number of ticks until the lower bound was reached, `None` if it never was'''
+
+  @property
+  def gap(self) -> Optional[int]:
+    '''Number of boxes above the lower bound'''
+    return self.box_count - self.lower_bound if self.box_count is not None else None
+
+def sweep_seed(
+    seed: int,
+    generate_problem,
+    Algorithm: type[OptimizationAlgorithm],
+    Mode,
+    max_ticks: int
+  ) -> SweepResult:
+  '''
+  Generates the instance of a seed and runs the algorithm on it until it reaches the lower bound,
+  stagnates or the tick budget runs out
+  '''
+  problem: BoxProblem = generate_problem(seed)
+  lower_bound = problem.get_lower_bound()
+  # The algorithm's own random decisions should be reproducible per seed as well
+  random.seed(seed)
+  reached_bound = ScoreAtMost(lower_bound)
+  result = run(Algorithm(problem, Mode), max_ticks, stop_conditions=[reached_bound, Stagnation()])
+
+  box_count = result.best_solution.get_heuristic_score().box_count if result.best_solution is not None else None
+  ticks_to_bound = result.ticks if result.stop_reason is reached_bound else None
+  return SweepResult(seed, lower_bound, box_count, ticks_to_bound)
+
+def rank_results(results: list[SweepResult], easiest: bool = False) -> list[SweepResult]:
+  '''
+  Orders the results from the hardest seed, i.e. the largest gap to the lower bound, to the easiest one.
+  With `easiest`, the seeds that reached the bound in the fewest ticks come first instead.
+  Runs without a complete solution count as the hardest.
+  '''
+  def hardness(r: SweepResult) -> tuple:
+    if r.gap is None:
+      return (1, 0, 0)
+    # Among equal gaps, reaching the bound later is harder
+    return (0, r.gap, r.ticks_to_bound if r.ticks_to_bound is not None else sys.maxsize)
+  # Equally hard seeds stay in ascending order either way
+  if easiest:
+    return sorted(results, key=lambda r: (hardness(r), r.seed))
+  return sorted(results, key=lambda r: (hardness(r), -r.seed), reverse=True)
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--seeds",
+    type=str,
+    help="Min-max range of seeds to sweep, the maximum excluded (e.g. 0-100)",
+    default="0-20"
+  )
+  parser.add_argument(
+    "--algorithm",
+    type=str,
+    help=f"Possible values: {[a.__name__ for a in OptimizationAlgorithm.__subclasses__()]}",
+    default="LocalSearch"
+  )
+  parser.add_argument(
+    "--mode",
+    type=str,
+    help=f"Possible values: {[m.__name__ for m in get_available_modes(None)]}",
+    default="Geometric"
+  )
+  parser.add_argument(
+    '--tick-number',
+    type=int,
+    help="Number of ticks each run may take at most",
+    default=20
+  )
+  add_problem_args(parser, rect_number=30, rect_size="1-10", box_length=15)
+  parser.add_argument(
+    "--easiest",
+    action="store_true",
+    help="List the seeds that reach the lower bound the fastest first instead of the hardest ones"
+  )
+  parser.add_argument(
+    "--top",
+    type=int,
+    help="Only list this many seeds"
+  )
+  add_log_args(parser)
+
+  args = parser.parse_args()
+
+  configure_logging(args)
+
+  Algorithm = get_algo_by_name(args.algorithm)
+  Mode = get_mode_by_name(Algorithm, args.mode)
+  if Algorithm is None or Mode is None:
+    raise ValueError(f"Unknown configuration: {args.algorithm} / {args.mode}")
+
+  def generate_problem(seed: int) -> BoxProblem:
+    return BoxProblem(
+      box_length=args.box_length,
+      n_rect=args.rect_number,
+      w_range=parse_range(args.rect_x),
+      h_range=parse_range(args.rect_y),
+      seed=seed
+    )
+
+  results = []
+  for seed in range(*[int(i) for i in args.seeds.split("-")]):
+    logging.info(f"Running seed {seed}")
+    results.append(sweep_seed(seed, generate_problem, Algorithm, Mode, args.tick_number))
+  ranked = rank_results(results, args.easiest)
+  if args.top is not None:
+    ranked = ranked[:args.top]
+
+  # Print results
+  table = Table("Rank", "Seed", "Lower bound", "Boxes", "Gap", "Ticks to bound")
+  for rank, r in enumerate(ranked, start=1):
+    table.add_row(
+      str(rank),
+      str(r.seed),
+      str(r.lower_bound),
+      str(r.box_count) if r.box_count is not None else "-",
+      str(r.gap) if r.gap is not None else "-",
+      str(r.ticks_to_bound) if r.ticks_to_bound is not None else "-"
+    )
+
+  console = Console()
+  console.print(table)
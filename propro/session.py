@@ -0,0 +1,97 @@
+'''
+Saving and resuming a whole run, i.e. the problem instance, the algorithm's state and the history of its scores.
+'''
+
+from __future__ import annotations
+import json
+import random
+from dataclasses import dataclass, asdict
+from typing import Optional
+
+from algorithms import OptimizationAlgorithm, get_algo_by_name
+from modes import Neighborhood, get_mode_by_name
+from problem.box_problem.box_problem import BoxProblem
+from problem.box_problem.box_solution import BoxSolution
+
+@dataclass
+class Session:
+  '''Snapshot of a run that can be written as JSON and resumed exactly where it left off'''
+  algorithm: str
+  '''Name of the algorithm class'''
+  mode: str
+  '''Name of the mode class the algorithm currently uses'''
+  problem: dict
+  '''Instance as produced by `BoxProblem.to_dict`'''
+  seed: Optional[int]
+  '''Seed the instance was generated with, `None` if it was given'''
+  solution: dict
+  '''Current solution as produced by `BoxSolution.to_dict`'''
+  last_moved_rect_ids: list[int]
+  '''Recently moved rects of the current solution, which neighborhoods skip'''
+  permissible_overlap: float
+  '''Currently allowed overlap of the current solution'''
+  algorithm_state: dict
+  '''Internal state as produced by `OptimizationAlgorithm.get_state`'''
+  mode_parameters: dict
+  '''Settings and state of the neighborhood, as produced by `Neighborhood.get_parameters`'''
+  box_count_history: list[int]
+  '''Box count after every tick so far'''
+  random_state: list
+  '''State of the global random number generator, so the run continues with the same random decisions'''
+
+  @classmethod
+  def capture(cls, algo: OptimizationAlgorithm, box_count_history: list[int]) -> Session:
+    '''Takes a snapshot of the given algorithm and its history'''
+    solution = algo.problem.current_solution
+    mode_parameters = dict(algo.strategy.get_parameters()) if issubclass(algo.strategy, Neighborhood) else {}
+    return cls(
+      algorithm=algo.__class__.__name__,
+      mode=algo.strategy.__name__,
+      problem=algo.problem.to_dict(),
+      seed=algo.problem.seed,
+      solution=solution.to_dict(),
+      last_moved_rect_ids=list(solution.last_moved_rect_ids),
+      permissible_overlap=solution.currently_permissible_overlap,
+      algorithm_state=algo.get_state(),
+      mode_parameters=mode_parameters,
+      box_count_history=list(box_count_history),
+      random_state=list(random.getstate())
+    )
+
+  def restore(self) -> OptimizationAlgorithm:
+    '''
+    Constructs the algorithm of this session and brings it back into the saved state.
+    Raises a ValueError if the algorithm or mode isn't known.
+    '''
+    Algorithm = get_algo_by_name(self.algorithm)
+    Mode = get_mode_by_name(Algorithm, self.mode) if Algorithm is not None else None
+    if Algorithm is None or Mode is None:
+      raise ValueError(f"Unknown configuration: {self.algorithm} / {self.mode}")
+
+    problem = BoxProblem.from_dict(self.problem)
+    problem.seed = self.seed
+    algo = Algorithm(problem, Mode)
+
+    # Constructing the algorithm starts it from scratch, so replace everything it set up
+    for name, value in self.mode_parameters.items():
+      setattr(Mode, name, value)
+    solution = BoxSolution.from_dict(self.solution)
+    solution.last_moved_rect_ids.extend(self.last_moved_rect_ids)
+    solution.currently_permissible_overlap = self.permissible_overlap
+    problem.current_solution = solution
+    algo.best_solution = solution
+    algo.set_state(self.algorithm_state)
+
+    version, internal_state, gauss_next = self.random_state
+    random.setstate((version, tuple(internal_state), gauss_next))
+    return algo
+
+def write_session(session: Session, path: str):
+  '''Writes a session as JSON to the given path'''
+  with open(path, "w", encoding="utf-8") as f:
+    json.dump(asdict(session), f)
+
+def read_session(path: str) -> Session:
+  '''Reads a session from a JSON file as written by `write_session`'''
+  with open(path, "r", encoding="utf-8") as f:
+    return Session(**json.load(f))
@@ -0,0 +1,75 @@
+'''
+Conditions on which a run of an algorithm is stopped early.
+'''
+
+from abc import ABC, abstractmethod
+from collections import deque
+from threading import Event
+
+from algorithms import OptimizationAlgorithm
+
+class StopCondition(ABC):
+  '''Base class for a condition that is checked after every tick of an algorithm'''
+
+  @abstractmethod
+  def is_met(self, algorithm: OptimizationAlgorithm) -> bool:
+    '''Returns true if the run should stop after the last tick'''
+
+  @abstractmethod
+  def __repr__(self):
+    pass
+
+class Stagnation(StopCondition):
+  '''Met if the score has been the same for the last `window` ticks'''
+  window: int
+  '''Number of equal scores in a row after which a run counts as converged'''
+
+  __last_scores: deque
+
+  def __init__(self, window: int = 5):
+    self.window = window
+    self.__last_scores = deque(maxlen=window)
+
+  def is_met(self, algorithm: OptimizationAlgorithm) -> bool:
+    self.__last_scores.append(algorithm.problem.current_solution.get_heuristic_score())
+    return self.__last_scores.count(self.__last_scores[0]) == self.__last_scores.maxlen
+
+  def __repr__(self):
+    return f"Stagnation({self.window})"
+
+class ScoreAtMost(StopCondition):
+  '''Met as soon as a complete solution uses at most `box_count` boxes'''
+  box_count: int
+  '''Target number of boxes'''
+
+  def __init__(self, box_count: int):
+    self.box_count = box_count
+
+  def is_met(self, algorithm: OptimizationAlgorithm) -> bool:
+    if not algorithm.has_complete_solution():
+      return False
+    score = algorithm.problem.current_solution.get_heuristic_score()
+    return score.is_valid() and score.box_count <= self.box_count
+
+  def __repr__(self):
+    return f"ScoreAtMost({self.box_count})"
+
+class Cancelled(StopCondition):
+  '''
+  Met once `cancel` was called, so a run can be stopped from the outside after its current tick.
+  It is safe to cancel from a signal handler or another thread.
+  '''
+  __cancelled: Event
+
+  def __init__(self):
+    self.__cancelled = Event()
+
+  def cancel(self):
+    '''Requests the run to stop after the current tick'''
+    self.__cancelled.set()
+
+  def is_met(self, algorithm: OptimizationAlgorithm) -> bool:
+    return self.__cancelled.is_set()
+
+  def __repr__(self):
+    return "Cancelled"
@@ -0,0 +1,148 @@
+'''
+Export of a solution as a standalone web page, which draws it on a browser canvas.
+The gui is bound to a desktop toolkit, but the layout of a solution is only a list of draw commands,
+so the page replays the same commands as the gui and needs nothing but a browser to be opened.
+'''
+
+import json
+from argparse import ArgumentParser
+from dataclasses import asdict
+from string import Template
+
+from checkpoint import read_solution
+from instances import read_problem
+from problem.box_problem.box_solution import BoxSolution
+from rendering import paint_solution, solution_extent, DrawCommand, DEFAULT_PALETTE, COLOR_BLIND_PALETTE, Palette
+
+PAGE_TEMPLATE = Template('''<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>$title</title>
+</head>
+<body>
+<p><a id="download" download="solution.json">Download solution</a></p>
+<canvas id="solution" width="$width" height="$height"></canvas>
+<script>
+const commands = $commands;
+const solution = $solution;
+const canvas = document.getElementById("solution");
+const context = canvas.getContext("2d");
+context.textAlign = "center";
+context.textBaseline = "middle";
+for (const command of commands) {
+  switch (command.kind) {
+    case "DrawRectangle": {
+      const [left, top] = command.top_left;
+      const [right, bottom] = command.bottom_right;
+      context.fillStyle = command.fill_color;
+      context.fillRect(left, top, right - left, bottom - top);
+      context.strokeStyle = "black";
+      context.strokeRect(left, top, right - left, bottom - top);
+      break;
+    }
+    case "DrawLine":
+      context.strokeStyle = command.color;
+      context.beginPath();
+      context.moveTo(...command.point_from);
+      context.lineTo(...command.point_to);
+      context.stroke();
+      break;
+    case "DrawText":
+      context.fillStyle = command.color;
+      context.fillText(command.text, ...command.location);
+      break;
+  }
+}
+// There is no file system to write to, so the solution is handed to the browser as a download
+const blob = new Blob([JSON.stringify(solution)], { type: "application/json" });
+document.getElementById("download").href = URL.createObjectURL(blob);
+</script>
+</body>
+</html>
+''')
+'''Page replaying the draw commands on a canvas, placeholders are filled in by `render_page`'''
+
+def command_to_dict(command: DrawCommand) -> dict:
+  '''Plain values of a draw command, tagged with its type so the page knows how to draw it'''
+  return { "kind": type(command).__name__, **asdict(command) }
+
+def render_page(
+    solution: BoxSolution,
+    scaling_factor: float,
+    show_rotation: bool = False,
+    show_capacity: bool = False,
+    palette: Palette = DEFAULT_PALETTE,
+    title: str = "Solution"
+  ) -> str:
+  '''Lays out the solution like the gui does and returns a web page that draws it'''
+  commands = paint_solution(solution, scaling_factor, True, show_rotation, show_capacity, palette)
+  (width, height) = solution_extent(solution, scaling_factor)
+  return PAGE_TEMPLATE.substitute(
+    title=title,
+    width=int(width) + 1,
+    height=int(height) + 1,
+    commands=json.dumps([command_to_dict(c) for c in commands]),
+    solution=json.dumps(solution.to_dict())
+  )
+
+if __name__ == "__main__":
+  parser = ArgumentParser()
+
+  parser.add_argument(
+    "--instance",
+    type=str,
+    help="Instance file as written by instances.py, drawn with every rect in its own box",
+  )
+  parser.add_argument(
+    "--solution",
+    type=str,
+    help="Solution file, e.g. a checkpoint, takes precedence over the instance"
+  )
+  parser.add_argument(
+    "--output",
+    type=str,
+    help="Path of the web page to write",
+    required=True
+  )
+  parser.add_argument(
+    "--scaling",
+    type=float,
+    help="Pixels per unit of the box side length",
+    default=10
+  )
+  parser.add_argument(
+    "--show-rotation",
+    action="store_true",
+    help="Mark rects that are flipped from their original orientation"
+  )
+  parser.add_argument(
+    "--show-capacity",
+    action="store_true",
+    help="Label every box with the number of rects from other boxes that would still fit in"
+  )
+  parser.add_argument(
+    "--color-blind",
+    action="store_true",
+    help="Use a color-blind friendly palette"
+  )
+
+  args = parser.parse_args()
+
+  if args.solution is not None:
+    shown = read_solution(args.solution)
+  elif args.instance is not None:
+    shown = read_problem(args.instance).current_solution
+  else:
+    parser.error("one of --solution or --instance is required")
+
+  page = render_page(
+    shown,
+    args.scaling,
+    show_rotation=args.show_rotation,
+    show_capacity=args.show_capacity,
+    palette=COLOR_BLIND_PALETTE if args.color_blind else DEFAULT_PALETTE,
+    title=args.solution or args.instance
+  )
+  with open(args.output, "w", encoding="utf-8") as f:
+    f.write(page)
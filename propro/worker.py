@@ -0,0 +1,187 @@
+'''
+Running the search in a background thread, which hands copies of the current solution over to the gui.
+'''
+
+from __future__ import annotations
+import logging
+import threading
+from copy import copy, deepcopy
+from dataclasses import dataclass
+from queue import Queue, Empty
+from typing import Callable, Optional
+
+from algorithms import OptimizationAlgorithm, ProgressStats
+from modes import NeighborhoodStats
+from problem.box_problem.box_solution import BoxSolution
+
+logger = logging.getLogger(__name__)
+
+@dataclass
+class Snapshot:
+  '''Copy of the state of an algorithm after a tick, which the gui can draw while the search goes on'''
+  solution: BoxSolution
+  '''Copy of the current solution, its boxes are marked for a redraw if they changed since the previous snapshot'''
+  progress: ProgressStats
+  '''Progress counters of the algorithm at the time of the snapshot'''
+  candidate_stats: NeighborhoodStats
+  '''Candidates the algorithm enumerated up to the snapshot'''
+  redraw_all: bool
+  '''Set for snapshots that may follow a dropped one or another instance, where the marks don't cover all changes'''
+
+class SearchWorker:
+  '''
+  Runs jobs on an algorithm in a background thread, so slow ticks don't freeze the gui
+  and fast algorithms aren't held up by drawing every single state.
+  After every tick, a job calls `after_tick`, which leaves a snapshot for the gui only once it took the previous one.
+  The gui thus draws at its own pace and ticks in between don't pay for a copy.
+  Only one job runs at a time, and the algorithm belongs to it until it is done.
+  Changes to the algorithm while a job runs go through `between_ticks`, so they never hit a tick halfway.
+  '''
+  algorithm: Optional[OptimizationAlgorithm]
+  '''Algorithm the running job works on, a job may switch it over to another one'''
+  box_count_history: list[int]
+  '''Box count after every tick, including the ones that never made it into a snapshot'''
+
+  __thread: Optional[threading.Thread]
+  __stop: threading.Event
+  __snapshots: Queue[Snapshot]
+  __on_done: Callable[[str], None]
+  __closing: bool
+  __job_active: bool
+  '''Set from starting a job until its last pending change was applied, guarded by `__lock`'''
+  __pending_changes: list[Callable[[], None]]
+  '''Changes waiting for the running job to finish its tick, guarded by `__lock`'''
+  __lock: threading.Lock
+
+  def __init__(self, on_done: Callable[[str], None]):
+    '''`on_done` is called from the worker thread with the message a job ended with'''
+    self.algorithm = None
+    self.box_count_history = []
+    self.__thread = None
+    self.__stop = threading.Event()
+    self.__snapshots = Queue(maxsize=1)
+    self.__on_done = on_done
+    self.__closing = False
+    self.__job_active = False
+    self.__pending_changes = []
+    self.__lock = threading.Lock()
+
+  def is_running(self) -> bool:
+    '''Checks whether a job is currently running'''
+    return self.__thread is not None and self.__thread.is_alive()
+
+  def start(self, algorithm: OptimizationAlgorithm, job: Callable[[SearchWorker], str]):
+    '''
+    Runs the job in the background, which gets this worker to report its ticks to and returns a message to end with.
+    A final snapshot of the algorithm is left once the job is done, so the gui always ends up at the last state.
+    Raises a RuntimeError if a job is still running.
+    '''
+    if self.is_running():
+      raise RuntimeError("The worker is still running a job")
+    self.algorithm = algorithm
+    self.__stop.clear()
+    with self.__lock:
+      self.__job_active = True
+
+    def run():
+      try:
+        message = job(self)
+      #pylint: disable=W0718
+      except Exception as e:
+        logger.exception("Search job failed")
+        message = f"Search failed: {e}"
+      # Changes that came in during the last tick still have to be applied before anyone else takes over
+      with self.__lock:
+        self.__job_active = False
+        changes = self.__take_pending_changes()
+      for change in changes:
+        change()
+      self.publish(force=True)
+      # Nobody is left to tell once the gui is closing
+      if not self.__closing:
+        self.__on_done(message)
+
+    self.__thread = threading.Thread(target=run, daemon=True)
+    self.__thread.start()
+
+  def should_stop(self) -> bool:
+    '''Checked by jobs before every tick, set once the worker was asked to stop'''
+    return self.__stop.is_set()
+
+  def request_stop(self):
+    '''Asks the running job to stop after its current tick, without waiting for it'''
+    self.__stop.set()
+
+  def stop(self, timeout: Optional[float] = None) -> bool:
+    '''
+    Asks the running job to stop after its current tick and waits for it, at most `timeout` seconds if given.
+    Its last snapshot is dropped, since whoever stops the job takes over the algorithm.
+    Returns whether the job is done, i.e. the algorithm can be touched again.
+    '''
+    self.__stop.set()
+    if self.__thread is not None:
+      self.__thread.join(timeout)
+    self.latest()
+    return not self.is_running()
+
+  def shutdown(self, timeout: float):
+    '''Stops the running job for good when the gui closes, giving it at most `timeout` seconds to finish its tick'''
+    self.__closing = True
+    self.stop(timeout)
+
+  def between_ticks(self, change: Callable[[], None]):
+    '''
+    Makes a change to the algorithm, e.g. a new neighborhood picked in the gui, without getting in the way of a tick.
+    If a job is running, the change is left for it to apply after its current tick, otherwise it is applied right away.
+    '''
+    with self.__lock:
+      if self.__job_active:
+        self.__pending_changes.append(change)
+        return
+    change()
+
+  def after_tick(self):
+    '''
+    Records the box count of the tick, applies the changes that came in during it
+    and leaves a snapshot if the gui is ready for one
+    '''
+    self.box_count_history.append(self.algorithm.problem.current_solution.get_heuristic_score().box_count)
+    with self.__lock:
+      changes = self.__take_pending_changes()
+    for change in changes:
+      change()
+    self.publish()
+
+  def __take_pending_changes(self) -> list[Callable[[], None]]:
+    '''Takes all pending changes out, to apply them without holding the lock'''
+    changes = self.__pending_changes
+    self.__pending_changes = []
+    return changes
+
+  def publish(self, force: bool = False):
+    '''
+    Leaves a snapshot of the algorithm for the gui, unless it hasn't taken the previous one yet.
+    With `force`, an untaken snapshot is replaced instead, e.g. for the last state of a job or a new instance,
+    and the gui is told to draw everything again.
+    '''
+    if self.__snapshots.full():
+      if not force:
+        return
+      self.latest()
+
+    solution = self.algorithm.problem.current_solution
+    snapshot = Snapshot(deepcopy(solution), copy(self.algorithm.progress), copy(self.algorithm.candidate_stats), force)
+    # The snapshot took over the marks of what changed, so the next one only gets what changes from now on
+    for box in solution.boxes.values():
+      box.needs_redraw = False
+      for rect in box.rects.values():
+        rect.highlighted = False
+    # Only the worker thread leaves snapshots, so the slot is still free
+    self.__snapshots.put_nowait(snapshot)
+
+  def latest(self) -> Optional[Snapshot]:
+    '''Takes the snapshot that was left for the gui, `None` if there is no new one'''
+    try:
+      return self.__snapshots.get_nowait()
+    except Empty:
+      return None